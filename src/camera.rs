@@ -0,0 +1,113 @@
+use super::{Hidden, Map, Position, Renderable, TileType};
+use rltk::{Point, Rltk, RGB};
+use specs::prelude::*;
+
+/// The part of the screen the map is drawn into; the rest belongs to the UI panel.
+pub const VIEWPORT_WIDTH: i32 = 80;
+pub const VIEWPORT_HEIGHT: i32 = 43;
+
+const SHOW_BOUNDARIES: bool = true;
+
+/// Returns the map coordinates of the top-left (inclusive) and bottom-right (exclusive) corners
+/// of the view, which is always centred on the player.
+pub fn get_screen_bounds(ecs: &World) -> (i32, i32, i32, i32) {
+    let player_pos = ecs.fetch::<Point>();
+
+    let min_x = player_pos.x - (VIEWPORT_WIDTH / 2);
+    let max_x = min_x + VIEWPORT_WIDTH;
+    let min_y = player_pos.y - (VIEWPORT_HEIGHT / 2);
+    let max_y = min_y + VIEWPORT_HEIGHT;
+
+    (min_x, max_x, min_y, max_y)
+}
+
+/// Translates a point on the screen (such as the mouse) into map coordinates.
+pub fn screen_to_map(ecs: &World, screen: (i32, i32)) -> Point {
+    let (min_x, _max_x, min_y, _max_y) = get_screen_bounds(ecs);
+    Point::new(screen.0 + min_x, screen.1 + min_y)
+}
+
+/// Translates a map position into screen coordinates, if it is inside the view.
+pub fn map_to_screen(ecs: &World, pos: Point) -> Option<Point> {
+    let (min_x, max_x, min_y, max_y) = get_screen_bounds(ecs);
+    if pos.x >= min_x && pos.x < max_x && pos.y >= min_y && pos.y < max_y {
+        Some(Point::new(pos.x - min_x, pos.y - min_y))
+    } else {
+        None
+    }
+}
+
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let (min_x, max_x, min_y, max_y) = get_screen_bounds(ecs);
+
+    // Render the map
+    for (y, ty) in (min_y..max_y).enumerate() {
+        for (x, tx) in (min_x..max_x).enumerate() {
+            if tx >= 0 && tx < map.width && ty >= 0 && ty < map.height {
+                let idx = map.xy_idx(tx, ty);
+                if map.revealed_tiles[idx] {
+                    let (glyph, fg, bg) = get_tile_glyph(idx, &map);
+                    ctx.set(x as i32, y as i32, fg, bg, glyph);
+                }
+            } else if SHOW_BOUNDARIES {
+                ctx.set(
+                    x as i32,
+                    y as i32,
+                    RGB::named(rltk::GRAY),
+                    RGB::named(rltk::BLACK),
+                    rltk::to_cp437('·'),
+                );
+            }
+        }
+    }
+
+    // Render the entities
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let mut data = (&positions, &renderables, !&hidden)
+        .join()
+        .map(|(pos, render, _)| (pos, render))
+        .collect::<Vec<_>>();
+    data.sort_by_key(|&a| std::cmp::Reverse(a.1.render_order));
+    for (pos, render) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if map.visible_tiles[idx] {
+            if let Some(screen) = map_to_screen(ecs, Point::new(pos.x, pos.y)) {
+                ctx.set(screen.x, screen.y, render.fg, render.bg, render.glyph);
+            }
+        }
+    }
+}
+
+fn get_tile_glyph(idx: usize, map: &Map) -> (rltk::FontCharType, RGB, RGB) {
+    let glyph;
+    let mut fg;
+    let bg = RGB::from_f32(0., 0., 0.);
+
+    match map.tiles[idx] {
+        TileType::Floor => {
+            glyph = rltk::to_cp437('.');
+            fg = RGB::from_f32(0.0, 0.5, 0.5);
+        }
+        TileType::Wall => {
+            glyph = rltk::to_cp437('#');
+            fg = RGB::from_f32(0., 1.0, 0.);
+        }
+        TileType::DownStairs => {
+            glyph = rltk::to_cp437('>');
+            fg = RGB::from_f32(0., 1.0, 1.0);
+        }
+        TileType::UpStairs => {
+            glyph = rltk::to_cp437('<');
+            fg = RGB::from_f32(0., 1.0, 1.0);
+        }
+    }
+    if !map.visible_tiles[idx] {
+        fg = fg.to_greyscale()
+    }
+
+    (glyph, fg, bg)
+}
@@ -0,0 +1,412 @@
+use super::{
+    gamelog::GameLog, raws, Abilities, Class, CombatStats, Debilities, Debility, Equippable,
+    Equipped, Experience, InBackpack, Name, Position,
+};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+/// The World of Dungeons classes a character can be.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum CharacterClass {
+    Fighter,
+    Thief,
+    Cleric,
+    Wizard,
+}
+
+impl CharacterClass {
+    pub const ALL: [CharacterClass; 4] = [
+        CharacterClass::Fighter,
+        CharacterClass::Thief,
+        CharacterClass::Cleric,
+        CharacterClass::Wizard,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CharacterClass::Fighter => "Fighter",
+            CharacterClass::Thief => "Thief",
+            CharacterClass::Cleric => "Cleric",
+            CharacterClass::Wizard => "Wizard",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            CharacterClass::Fighter => "Tough and well armed, at home in the thick of a fight.",
+            CharacterClass::Thief => "Quick fingers for locks and traps, and a key or two.",
+            CharacterClass::Cleric => "A shield, a blessing, and healing that goes further.",
+            CharacterClass::Wizard => "Frail, but their scrolls hit much harder.",
+        }
+    }
+
+    /// Hit points before Constitution is added.
+    pub fn base_hp(self) -> i32 {
+        match self {
+            CharacterClass::Fighter => 24,
+            CharacterClass::Thief => 18,
+            CharacterClass::Cleric => 20,
+            CharacterClass::Wizard => 14,
+        }
+    }
+
+    pub fn starting_abilities(self) -> Vec<Ability> {
+        match self {
+            CharacterClass::Fighter => vec![Ability::WeaponMaster],
+            CharacterClass::Thief => vec![Ability::Tinker],
+            CharacterClass::Cleric => vec![Ability::Healer],
+            CharacterClass::Wizard => vec![Ability::Spellcraft],
+        }
+    }
+
+    /// The abilities the class can pick up as it levels.
+    pub fn advanced_abilities(self) -> &'static [Ability] {
+        match self {
+            CharacterClass::Fighter => &[Ability::Armored, Ability::Tough],
+            CharacterClass::Thief => &[Ability::Lightfooted, Ability::KeenEyed],
+            CharacterClass::Cleric => &[Ability::Armored, Ability::Devoted],
+            CharacterClass::Wizard => &[Ability::ArcaneReach, Ability::KeenEyed],
+        }
+    }
+
+    /// The die rolled (plus CON) for extra hit points on levelling up.
+    pub fn hp_die(self) -> i32 {
+        match self {
+            CharacterClass::Fighter => 10,
+            CharacterClass::Thief => 6,
+            CharacterClass::Cleric => 8,
+            CharacterClass::Wizard => 4,
+        }
+    }
+
+    /// What the class starts with, by raw name; `true` means it starts equipped.
+    pub fn starting_equipment(self) -> &'static [(&'static str, bool)] {
+        match self {
+            CharacterClass::Fighter => &[
+                ("Longsword", true),
+                ("Shield", true),
+                ("Chainmail", true),
+                ("Health Potion", false),
+            ],
+            CharacterClass::Thief => &[
+                ("Dagger", true),
+                ("Leather Armor", true),
+                ("Sling", true),
+                ("Sling Stones", false),
+                ("Skeleton Key", false),
+                ("Skeleton Key", false),
+                ("Health Potion", false),
+            ],
+            CharacterClass::Cleric => &[
+                ("Dagger", true),
+                ("Shield", true),
+                ("Chainmail", true),
+                ("Health Potion", false),
+                ("Health Potion", false),
+            ],
+            CharacterClass::Wizard => &[
+                ("Dagger", true),
+                ("Magic Missile Scroll", false),
+                ("Magic Missile Scroll", false),
+                ("Confusion Scroll", false),
+            ],
+        }
+    }
+}
+
+/// World of Dungeons skills. Having the right skill adds +1 to a roll.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum Skill {
+    Athletics,
+    Awareness,
+    Deception,
+    Decipher,
+    Heal,
+    Leadership,
+    Lore,
+    Stealth,
+    Survival,
+}
+
+impl Skill {
+    pub const ALL: [Skill; 9] = [
+        Skill::Athletics,
+        Skill::Awareness,
+        Skill::Deception,
+        Skill::Decipher,
+        Skill::Heal,
+        Skill::Leadership,
+        Skill::Lore,
+        Skill::Stealth,
+        Skill::Survival,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Skill::Athletics => "Athletics",
+            Skill::Awareness => "Awareness",
+            Skill::Deception => "Deception",
+            Skill::Decipher => "Decipher",
+            Skill::Heal => "Heal",
+            Skill::Leadership => "Leadership",
+            Skill::Lore => "Lore",
+            Skill::Stealth => "Stealth",
+            Skill::Survival => "Survival",
+        }
+    }
+}
+
+/// How many skills a new character picks.
+pub const SKILL_CHOICES: usize = 2;
+
+/// Special abilities, granted by class.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum Ability {
+    /// +1 damage with melee hits.
+    WeaponMaster,
+    /// +1 to picking locks and disarming traps.
+    Tinker,
+    /// Healing items restore more hit points, and cure a debility.
+    Healer,
+    /// Damaging scrolls hit harder.
+    Spellcraft,
+    /// Takes 1 less damage from melee hits.
+    Armored,
+    /// Extra hit points, straight away.
+    Tough,
+    /// +1 to getting out of the way of traps.
+    Lightfooted,
+    /// +1 to spotting hidden traps.
+    KeenEyed,
+    /// Resting heals twice as fast.
+    Devoted,
+    /// Targeted scrolls reach further.
+    ArcaneReach,
+}
+
+impl Ability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Ability::WeaponMaster => "Weapon Master",
+            Ability::Tinker => "Tinker",
+            Ability::Healer => "Healer",
+            Ability::Spellcraft => "Spellcraft",
+            Ability::Armored => "Armored",
+            Ability::Tough => "Tough",
+            Ability::Lightfooted => "Lightfooted",
+            Ability::KeenEyed => "Keen Eyed",
+            Ability::Devoted => "Devoted",
+            Ability::ArcaneReach => "Arcane Reach",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Ability::WeaponMaster => "+1 damage with melee hits.",
+            Ability::Tinker => "+1 to picking locks and disarming traps.",
+            Ability::Healer => "Healing items heal more, and cure a debility.",
+            Ability::Spellcraft => "Damaging scrolls hit harder.",
+            Ability::Armored => "Take 1 less damage from melee hits.",
+            Ability::Tough => "Gain extra hit points.",
+            Ability::Lightfooted => "+1 to avoiding traps you walk into.",
+            Ability::KeenEyed => "+1 to spotting hidden traps.",
+            Ability::Devoted => "Resting heals twice as fast.",
+            Ability::ArcaneReach => "Targeted scrolls reach further.",
+        }
+    }
+}
+
+/// The extra healing the Healer ability adds to healing items.
+pub const HEALER_BONUS: i32 = 4;
+/// One chance in this many, each turn spent resting, of shaking off a debility.
+pub const REST_RECOVERY_CHANCE: i32 = 20;
+/// The extra damage the Spellcraft ability adds to damaging scrolls.
+pub const SPELLCRAFT_BONUS: i32 = 4;
+/// The extra hit points the Tough ability grants.
+pub const TOUGH_BONUS: i32 = 5;
+/// The extra range the Arcane Reach ability gives targeted scrolls.
+pub const ARCANE_REACH_BONUS: i32 = 2;
+/// No stat can be raised past +3 by levelling up.
+pub const MAX_STAT: i32 = 3;
+
+pub const STAT_NAMES: [&str; 6] = ["STR", "DEX", "CON", "INT", "WIS", "CHA"];
+/// Where the stats the game rolls with sit in `STAT_NAMES`.
+pub const STR: usize = 0;
+pub const DEX: usize = 1;
+pub const CON: usize = 2;
+pub const WIS: usize = 4;
+/// The standard array: one stat at +2, two at +1 and the rest at 0.
+pub const STAT_ARRAY: [i32; 3] = [2, 1, 1];
+
+/// The character being put together on the creation screens.
+#[derive(Clone)]
+pub struct CharacterDraft {
+    pub class: CharacterClass,
+    /// STR, DEX, CON, INT, WIS and CHA, in that order.
+    pub stats: [i32; 6],
+    pub skills: Vec<Skill>,
+    pub name: String,
+}
+
+impl CharacterDraft {
+    pub fn new() -> CharacterDraft {
+        CharacterDraft {
+            class: CharacterClass::Fighter,
+            stats: [0; 6],
+            skills: Vec::new(),
+            name: String::new(),
+        }
+    }
+
+    /// World of Dungeons rolling: 3d6 per stat, where 16+ is +2, 13-15 is +1 and anything
+    /// else is 0.
+    pub fn roll_stats(&mut self, rng: &mut rltk::RandomNumberGenerator) {
+        for stat in self.stats.iter_mut() {
+            *stat = match rng.roll_dice(3, 6) {
+                16..=18 => 2,
+                13..=15 => 1,
+                _ => 0,
+            };
+        }
+    }
+
+    pub fn max_hp(&self) -> i32 {
+        self.class.base_hp() + 3 * self.stats[2]
+    }
+}
+
+impl Default for CharacterDraft {
+    fn default() -> Self {
+        CharacterDraft::new()
+    }
+}
+
+/// The most adventurers a party can have.
+pub const MAX_PARTY_SIZE: usize = 6;
+
+/// Everyone finished on the creation screens so far, in marching order.
+#[derive(Clone, Default)]
+pub struct PartyDraft {
+    pub members: Vec<CharacterDraft>,
+}
+
+/// A turn's rest with nothing in sight: a hit point back (two for the Devoted) and a chance of
+/// shaking off the oldest debility, which is returned if it goes.
+pub fn rest(
+    stats: &mut CombatStats,
+    debilities: Option<&mut Debilities>,
+    devoted: bool,
+    rng: &mut rltk::RandomNumberGenerator,
+) -> Option<Debility> {
+    let heal = if devoted { 2 } else { 1 };
+    stats.hp = i32::min(stats.hp + heal, stats.max_hp);
+    match debilities {
+        Some(debilities)
+            if !debilities.debilities.is_empty() && rng.roll_dice(1, REST_RECOVERY_CHANCE) == 1 =>
+        {
+            Some(debilities.debilities.remove(0))
+        }
+        _ => None,
+    }
+}
+
+/// Puts the class's starting kit in the player's hands and backpack.
+pub fn give_starting_equipment(ecs: &mut World, player: Entity, class: CharacterClass) {
+    for (name, equip) in class.starting_equipment().iter() {
+        let item = raws::spawn_named_entity(ecs, name, 0, 0)
+            .expect("Starting equipment is checked when the raws load");
+        ecs.write_storage::<Position>().remove(item);
+        let slot = ecs.read_storage::<Equippable>().get(item).map(|e| e.slot);
+        match slot {
+            Some(slot) if *equip => {
+                ecs.write_storage::<Equipped>()
+                    .insert(
+                        item,
+                        Equipped {
+                            owner: player,
+                            slot,
+                        },
+                    )
+                    .expect("Unable to equip starting item");
+            }
+            _ => {
+                ecs.write_storage::<InBackpack>()
+                    .insert(item, InBackpack { owner: player })
+                    .expect("Unable to pack starting item");
+            }
+        }
+    }
+}
+
+/// The stats of a `CombatStats` in `STAT_NAMES` order.
+pub fn stat_values(stats: &CombatStats) -> [i32; 6] {
+    [
+        stats.str, stats.dex, stats.con, stats.int, stats.wis, stats.cha,
+    ]
+}
+
+/// One of the stats of a `CombatStats`, picked by its place in `STAT_NAMES`.
+pub fn stat_mut(stats: &mut CombatStats, stat: usize) -> &mut i32 {
+    match stat {
+        0 => &mut stats.str,
+        1 => &mut stats.dex,
+        2 => &mut stats.con,
+        3 => &mut stats.int,
+        4 => &mut stats.wis,
+        _ => &mut stats.cha,
+    }
+}
+
+/// Advances `member` of the party a level: spends the experience, raises `stat` by one, rolls
+/// the class hit die (plus CON) for extra HP and learns `ability`.
+pub fn level_up(ecs: &mut World, member: Entity, stat: Option<usize>, ability: Option<Ability>) {
+    let player = *ecs.fetch::<Entity>();
+    let mut experience = ecs.write_storage::<Experience>();
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    let mut abilities = ecs.write_storage::<Abilities>();
+    let classes = ecs.read_storage::<Class>();
+    let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+    let mut log = ecs.write_resource::<GameLog>();
+
+    let exp = experience
+        .get_mut(member)
+        .expect("The party member has no experience");
+    exp.xp -= exp.next_level();
+    exp.level += 1;
+    let level = exp.level;
+    let stats = combat_stats
+        .get_mut(member)
+        .expect("The party member has no stats");
+
+    if let Some(stat) = stat {
+        *stat_mut(stats, stat) += 1;
+        // Constitution is worth 3 hit points a point, as at character creation
+        if stat == CON {
+            stats.max_hp += 3;
+            stats.hp += 3;
+        }
+    }
+
+    let die = classes.get(member).map_or(6, |c| c.class.hp_die());
+    let mut hp_gain = i32::max(1, rng.roll_dice(1, die) + stats.con);
+    if ability == Some(Ability::Tough) {
+        hp_gain += TOUGH_BONUS;
+    }
+    stats.max_hp += hp_gain;
+    stats.hp += hp_gain;
+
+    if let (Some(ability), Some(known)) = (ability, abilities.get_mut(member)) {
+        known.abilities.push(ability);
+    }
+
+    match ecs.read_storage::<Name>().get(member) {
+        Some(name) if member != player => log.entries.push(format!(
+            "{} reaches level {}, and gains {} hp.",
+            name.name, level, hp_gain
+        )),
+        _ => log.entries.push(format!(
+            "Welcome to level {}! You gain {} hp.",
+            level, hp_gain
+        )),
+    }
+}
@@ -1,9 +1,13 @@
+use super::raws::RawMaster;
 use rltk::RGB;
 use serde::{Deserialize, Serialize};
 use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{ConvertSaveload, Marker};
+use specs::storage::{GenericReadStorage, MaskedStorage};
 use specs_derive::*;
+use std::collections::HashMap;
+use std::ops::Deref;
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Position {
@@ -11,6 +15,14 @@ pub struct Position {
     pub y: i32,
 }
 
+/// Where an entity is on a level the player isn't currently on.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct OtherLevelPosition {
+    pub x: i32,
+    pub y: i32,
+    pub depth: i32,
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
@@ -22,6 +34,13 @@ pub struct Renderable {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
+/// One of the adventurers in the player's party; whichever of them is under control right now
+/// is also the `Player`. `slot` is their place in the marching order.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct PartyMember {
+    pub slot: usize,
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
@@ -32,6 +51,67 @@ pub struct Viewshed {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+/// The damage a monster does with its own teeth and claws.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct NaturalAttack {
+    pub damage: rltk::DiceType,
+}
+
+/// A monster's own way of hurting things from afar: spines, spit or a bow it never runs out of
+/// arrows for. `range` is in tiles.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct NaturalRangedAttack {
+    pub damage: rltk::DiceType,
+    pub range: i32,
+}
+
+/// A spell a monster knows. The spell is an entity of its own, carrying the same effects an item
+/// would, and once cast it can't be cast again for `cooldown` of its caster's goes.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct KnownSpell {
+    pub caster: Entity,
+    pub cooldown: i32,
+    pub ready_in: i32,
+}
+
+/// A monster taking aim, to shoot on its next go.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TakingAim {}
+
+/// A monster calling up a spell, to cast on its next go.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Casting {
+    pub spell: Entity,
+}
+
+/// Hide, scales or whatever else a monster has instead of worn armor.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct NaturalArmor {
+    pub armor: i32,
+}
+
+/// Dungeon World style monster tags.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum MonsterTag {
+    /// Hard to land a blow on: -1 to attack it.
+    Small,
+    /// Fights as a pack: +1 damage for each packmate also next to its target.
+    Horde,
+    /// Runs once badly hurt, without testing its morale.
+    Cautious,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MonsterTags {
+    pub tags: Vec<MonsterTag>,
+}
+
+impl MonsterTags {
+    pub fn has(tags: &ReadStorage<MonsterTags>, entity: Entity, tag: MonsterTag) -> bool {
+        tags.get(entity).is_some_and(|t| t.tags.contains(&tag))
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name: String,
@@ -52,6 +132,77 @@ pub struct CombatStats {
     pub hp: i32,
 }
 
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Class {
+    pub class: super::character::CharacterClass,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Skills {
+    pub skills: Vec<super::character::Skill>,
+}
+
+impl Skills {
+    /// The +1 a roll gets when this entity has `skill`.
+    pub fn bonus(
+        skills: &ReadStorage<Skills>,
+        entity: Entity,
+        skill: super::character::Skill,
+    ) -> i32 {
+        match skills.get(entity) {
+            Some(s) if s.skills.contains(&skill) => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Abilities {
+    pub abilities: Vec<super::character::Ability>,
+}
+
+impl Abilities {
+    pub fn has(
+        abilities: &ReadStorage<Abilities>,
+        entity: Entity,
+        ability: super::character::Ability,
+    ) -> bool {
+        abilities
+            .get(entity)
+            .is_some_and(|a| a.abilities.contains(&ability))
+    }
+}
+
+/// Left by bargaining with Death; each mark is -1 to later Last Breath rolls.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct DeathsMark {
+    pub marks: i32,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Experience {
+    pub level: i32,
+    pub xp: i32,
+}
+
+impl Experience {
+    /// As in Dungeon World, the next level takes the current level + 7 experience.
+    pub fn next_level(&self) -> i32 {
+        self.level + 7
+    }
+
+    pub fn can_level_up(&self) -> bool {
+        self.xp >= self.next_level()
+    }
+
+    /// Gives `entity` some experience, if it is the sort of thing that learns.
+    pub fn mark(experience: &mut WriteStorage<Experience>, entity: Entity, amount: i32) {
+        if let Some(exp) = experience.get_mut(entity) {
+            exp.xp += amount;
+        }
+    }
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToMelee {
     pub target: Entity,
@@ -59,22 +210,511 @@ pub struct WantsToMelee {
 
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    /// Each hit, and the party slot of whoever dealt it if one of the party did (so kills can
+    /// earn them experience). A slot rather than an entity, so it saves as plain data.
+    pub amount: Vec<(i32, Option<usize>)>,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        dealt_by: Option<usize>,
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, dealt_by));
         } else {
             let dmg = SufferDamage {
-                amount: vec![amount],
+                amount: vec![(amount, dealt_by)],
             };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct BlocksVisibility {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Door {
+    pub open: bool,
+}
+
+impl Door {
+    /// Swings a closed door open, so it no longer blocks movement or sight.
+    pub fn open_door(
+        door_entity: Entity,
+        doors: &mut WriteStorage<Door>,
+        blocks_movement: &mut WriteStorage<BlocksTile>,
+        blocks_visibility: &mut WriteStorage<BlocksVisibility>,
+        renderables: &mut WriteStorage<Renderable>,
+    ) {
+        if let Some(door) = doors.get_mut(door_entity) {
+            door.open = true;
+        }
+        blocks_movement.remove(door_entity);
+        blocks_visibility.remove(door_entity);
+        if let Some(glyph) = renderables.get_mut(door_entity) {
+            glyph.glyph = rltk::to_cp437('\'');
+        }
+    }
+}
+
+/// A door that needs a key or a lockpicking roll before it will open.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Locked {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Key {}
+
+/// Something the player hasn't noticed yet; it isn't drawn or described until it's found.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum TrapKind {
+    Pit,
+    Darts,
+    Teleport,
+    Alarm,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Trap {
+    pub kind: TrapKind,
+}
+
+/// Set on the player when they step onto a new tile, so traps there get a chance to go off.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntityMoved {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToSearch {}
+
+/// The player is letting the rest of this round go first.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToDelay {}
+
+/// Ready to strike back at the first thing to attack, until the player's next turn.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Guarding {}
+
+/// A combatant's initiative roll, which sets their place in the turn order for as long as the
+/// encounter lasts. Highest goes first.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Initiative {
+    pub roll: i32,
+}
+
+impl Initiative {
+    /// Everyone in the encounter, in the order they act. The player wins any ties.
+    pub fn turn_order<D>(
+        entities: &Entities,
+        initiative: &Storage<Initiative, D>,
+        player: Entity,
+    ) -> Vec<Entity>
+    where
+        D: Deref<Target = MaskedStorage<Initiative>>,
+    {
+        let mut order: Vec<(Entity, i32)> = (entities, initiative)
+            .join()
+            .map(|(entity, initiative)| (entity, initiative.roll))
+            .collect();
+        order.sort_by_key(|(entity, roll)| (-roll, *entity != player, entity.id()));
+        order.into_iter().map(|(entity, _)| entity).collect()
+    }
+}
+
+/// A monster that has heard something, and is going to look.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Alerted {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Something loud enough, this turn, to be heard `volume` tiles away.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MakesNoise {
+    pub volume: i32,
+}
+
+impl MakesNoise {
+    /// Adds a noise from `entity`; only the loudest it makes in a turn counts.
+    pub fn make(noises: &mut WriteStorage<MakesNoise>, entity: Entity, volume: i32) {
+        if let Some(noise) = noises.get_mut(entity) {
+            noise.volume = i32::max(noise.volume, volume);
+        } else {
+            noises
+                .insert(entity, MakesNoise { volume })
+                .expect("Unable to insert noise");
+        }
+    }
+}
+
+/// Where the player is travelling to, a step each turn.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Destination {
+    /// The nearest place they haven't seen yet.
+    Unexplored,
+    /// The stairs down, if they've found them.
+    Stairs,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Travelling {
+    pub destination: Destination,
+}
+
+/// Moving carefully: at half speed, but with a Stealth roll to keep each step quiet.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Sneaking {}
+
+/// What a monster is up to.
+#[derive(Component, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AiState {
+    /// Resting where it is; it only notices the player up close.
+    Idle,
+    /// Roaming about at random.
+    Wander,
+    /// Chasing whatever it means to attack, last seen at `x`, `y`.
+    Hunt { x: i32, y: i32 },
+    /// Looking for its quarry where it was last seen, or for whatever was heard.
+    Search { x: i32, y: i32 },
+    /// Running from danger for a number of turns.
+    Flee { turns: i32 },
+}
+
+impl AiState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AiState::Idle => "idle",
+            AiState::Wander => "wandering",
+            AiState::Hunt { .. } => "hunting",
+            AiState::Search { .. } => "searching",
+            AiState::Flee { .. } => "fleeing",
+        }
+    }
+}
+
+/// A monster's nerve: added to the 2d6 it rolls the first time it's badly hurt, to see whether
+/// it stands (10+), falls back for a while (7-9) or breaks and runs (6-).
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Morale {
+    pub morale: i32,
+    pub tested: bool,
+}
+
+/// The faction the player belongs to. The raws can give factions a reaction to it, like any other.
+pub const PLAYER_FACTION: &str = "Player";
+
+/// How one faction treats members of another.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Reaction {
+    /// Leaves them be.
+    Ignore,
+    /// Hunts them down.
+    Attack,
+    /// Keeps away from them.
+    Flee,
+}
+
+/// Whose side a creature is on.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
+impl Faction {
+    /// How `entity` reacts to `other`, going by the raws' reaction table. A faction `other`
+    /// has wronged attacks them even if it would otherwise leave them be; anyone without a
+    /// faction is ignored.
+    pub fn reaction(
+        raws: &RawMaster,
+        factions: &ReadStorage<Faction>,
+        reputations: &impl GenericReadStorage<Component = Reputation>,
+        entity: Entity,
+        other: Entity,
+    ) -> Reaction {
+        let (mine, theirs) = match (factions.get(entity), factions.get(other)) {
+            (Some(mine), Some(theirs)) => (mine, theirs),
+            _ => return Reaction::Ignore,
+        };
+        let reaction = raws.reaction(&mine.name, &theirs.name);
+        let wronged = reputations
+            .get(other)
+            .is_some_and(|r| r.standing(&mine.name) < 0);
+        if wronged && reaction == Reaction::Ignore {
+            Reaction::Attack
+        } else {
+            reaction
+        }
+    }
+}
+
+/// How each faction regards the player. Everyone starts at 0; hurting a member of a faction
+/// costs standing with all of it, and below 0 the faction turns hostile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Reputation {
+    pub standing: HashMap<String, i32>,
+}
+
+impl Reputation {
+    pub fn standing(&self, faction: &str) -> i32 {
+        self.standing.get(faction).copied().unwrap_or(0)
+    }
+
+    /// Changes the standing of whoever's reputation this is with `faction` by `change`.
+    pub fn adjust(
+        reputations: &mut WriteStorage<Reputation>,
+        entity: Entity,
+        faction: &str,
+        change: i32,
+    ) {
+        if let Some(reputation) = reputations.get_mut(entity) {
+            *reputation.standing.entry(faction.to_string()).or_insert(0) += change;
+        }
+    }
+}
+
+/// Conditions that last a number of turns.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Status {
+    /// Loses a hit point each turn.
+    Poisoned,
+    /// Gains a hit point each turn.
+    Regenerating,
+    /// Stumbles about at random.
+    Confused,
+    /// Can't act at all.
+    Stunned,
+    /// Can't act until it wears off, or something hurts them.
+    Sleeping,
+    /// Only acts every other turn.
+    Slowed,
+    /// Gets an extra action every other turn.
+    Hasted,
+    /// Can only see what's right next to them.
+    Blinded,
+}
+
+impl Status {
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Poisoned => "Poisoned",
+            Status::Regenerating => "Regenerating",
+            Status::Confused => "Confused",
+            Status::Stunned => "Stunned",
+            Status::Sleeping => "Asleep",
+            Status::Slowed => "Slowed",
+            Status::Hasted => "Hasted",
+            Status::Blinded => "Blinded",
+        }
+    }
+
+    /// Whether it's something to be rid of, rather than a blessing.
+    pub fn harmful(self) -> bool {
+        !matches!(self, Status::Regenerating | Status::Hasted)
+    }
+
+    /// Whether another dose adds to the turns left, rather than just topping them back up.
+    pub fn stacks(self) -> bool {
+        self == Status::Poisoned
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub status: Status,
+    pub turns: i32,
+}
+
+/// Everything currently affecting an entity; `StatusEffectSystem` counts them down.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    /// Puts `status` on `entity` for `turns` turns, stacking or refreshing whatever is already
+    /// there.
+    pub fn add(
+        store: &mut WriteStorage<StatusEffects>,
+        entity: Entity,
+        status: Status,
+        turns: i32,
+    ) {
+        if let Some(existing) = store.get_mut(entity) {
+            match existing.effects.iter_mut().find(|e| e.status == status) {
+                Some(effect) if status.stacks() => effect.turns += turns,
+                Some(effect) => effect.turns = i32::max(effect.turns, turns),
+                None => existing.effects.push(StatusEffect { status, turns }),
+            }
+        } else {
+            store
+                .insert(
+                    entity,
+                    StatusEffects {
+                        effects: vec![StatusEffect { status, turns }],
+                    },
+                )
+                .expect("Unable to insert status");
+        }
+    }
+
+    pub fn has(&self, status: Status) -> bool {
+        self.turns(status).is_some()
+    }
+
+    pub fn turns(&self, status: Status) -> Option<i32> {
+        self.effects
+            .iter()
+            .find(|e| e.status == status)
+            .map(|e| e.turns)
+    }
+
+    /// Whether the entity is out of it altogether, and can't even defend itself.
+    pub fn helpless(&self) -> bool {
+        self.has(Status::Stunned) || self.has(Status::Sleeping)
+    }
+}
+
+/// The energy a normal speed gains each turn, and a normal action costs.
+pub const NORMAL_SPEED: i32 = 100;
+/// What most things cost to do: a step, a swing, a shot, a scroll.
+pub const ACTION_COST: i32 = 100;
+/// Drinking a potion is quicker.
+pub const QUICK_ACTION_COST: i32 = 50;
+/// Swinging a two-handed weapon takes longer.
+pub const HEAVY_ATTACK_COST: i32 = 150;
+
+/// Everyone gains their speed in energy each turn and spends it on actions, acting whenever they
+/// have any to spend; a fast entity can act more than once in a turn, a slow one less than once.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Energy {
+    pub energy: i32,
+    /// The energy gained each turn, before haste, slow and encumbrance.
+    pub speed: i32,
+}
+
+impl Energy {
+    pub fn new(speed: i32) -> Energy {
+        Energy { energy: 0, speed }
+    }
+
+    /// Whether `entity` has the energy to act; anyone without an `Energy` always does.
+    pub fn ready(energy: &impl GenericReadStorage<Component = Energy>, entity: Entity) -> bool {
+        energy.get(entity).is_none_or(|e| e.energy >= 0)
+    }
+
+    pub fn spend(energy: &mut WriteStorage<Energy>, entity: Entity, cost: i32) {
+        if let Some(energy) = energy.get_mut(entity) {
+            energy.energy -= cost;
+        }
+    }
+}
+
+/// Dungeon World debilities: lasting harm, each worth -1 to rolls with one stat until cured.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Debility {
+    Weak,
+    Shaky,
+    Sick,
+    Stunned,
+    Confused,
+    Scarred,
+}
+
+impl Debility {
+    /// In `STAT_NAMES` order, so each sits at the index of the stat it hurts.
+    pub const ALL: [Debility; 6] = [
+        Debility::Weak,
+        Debility::Shaky,
+        Debility::Sick,
+        Debility::Stunned,
+        Debility::Confused,
+        Debility::Scarred,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Debility::Weak => "Weak",
+            Debility::Shaky => "Shaky",
+            Debility::Sick => "Sick",
+            Debility::Stunned => "Stunned",
+            Debility::Confused => "Confused",
+            Debility::Scarred => "Scarred",
+        }
+    }
+
+    /// The stat it takes 1 off, by its place in `STAT_NAMES`.
+    pub fn stat(self) -> usize {
+        Debility::ALL.iter().position(|d| *d == self).unwrap()
+    }
+
+    /// Such as "Weak (-1 STR)".
+    pub fn describe(self) -> String {
+        format!(
+            "{} (-1 {})",
+            self.name(),
+            super::character::STAT_NAMES[self.stat()]
+        )
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Debilities {
+    pub debilities: Vec<Debility>,
+}
+
+impl Debilities {
+    /// The -1 a roll with `stat` takes when `entity` has the matching debility.
+    pub fn penalty(
+        debilities: &impl GenericReadStorage<Component = Debilities>,
+        entity: Entity,
+        stat: usize,
+    ) -> i32 {
+        match debilities.get(entity) {
+            Some(d) if d.debilities.contains(&Debility::ALL[stat]) => -1,
+            _ => 0,
+        }
+    }
+
+    /// Gives `entity` the debility; returns false if they already had it.
+    pub fn inflict(
+        debilities: &mut WriteStorage<Debilities>,
+        entity: Entity,
+        debility: Debility,
+    ) -> bool {
+        if let Some(existing) = debilities.get_mut(entity) {
+            if existing.debilities.contains(&debility) {
+                return false;
+            }
+            existing.debilities.push(debility);
+        } else {
+            debilities
+                .insert(
+                    entity,
+                    Debilities {
+                        debilities: vec![debility],
+                    },
+                )
+                .expect("Unable to insert debility");
+        }
+        true
+    }
+}
+
+/// Passes a debility on to whoever the monster hurts.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsDebility {
+    pub debility: Debility,
+}
+
+/// Clears every debility from whoever uses it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct CuresDebilities {}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
@@ -96,8 +736,10 @@ pub struct AreaOfEffect {
     pub radius: i32,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct Confusion {
+/// Puts a status on whatever the item is used on, or whoever the monster hits.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+    pub status: Status,
     pub turns: i32,
 }
 
@@ -123,6 +765,11 @@ pub struct WantsToUseItem {
     pub target: Option<rltk::Point>,
 }
 
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToShoot {
+    pub target: rltk::Point,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToDropItem {
     pub item: Entity,
@@ -133,10 +780,35 @@ pub struct WantsToRemoveItem {
     pub item: Entity,
 }
 
-#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum EquipmentSlot {
     Melee,
-    Armor,
+    /// A bow, sling or the like, fired with `Ammo`.
+    Ranged,
+    Head,
+    Body,
+    /// A shield, or anything else held in the other hand.
+    OffHand,
+    Hands,
+    Feet,
+    Ring,
+    Amulet,
+}
+
+impl EquipmentSlot {
+    pub fn name(self) -> &'static str {
+        match self {
+            EquipmentSlot::Melee => "Weapon",
+            EquipmentSlot::Ranged => "Ranged",
+            EquipmentSlot::Head => "Head",
+            EquipmentSlot::Body => "Body",
+            EquipmentSlot::OffHand => "Off hand",
+            EquipmentSlot::Hands => "Hands",
+            EquipmentSlot::Feet => "Feet",
+            EquipmentSlot::Ring => "Ring",
+            EquipmentSlot::Amulet => "Amulet",
+        }
+    }
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
@@ -150,14 +822,228 @@ pub struct Equipped {
     pub slot: EquipmentSlot,
 }
 
+/// Dungeon World weapon tags.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum WeaponTag {
+    /// Only reaches an adjacent enemy.
+    Hand,
+    /// Only reaches an adjacent enemy.
+    Close,
+    /// Reaches an enemy two tiles away.
+    Reach,
+    /// Attacks with DEX instead of STR.
+    Precise,
+    /// Hits roll damage twice and keep the better.
+    Messy,
+    /// Hits knock the target back a tile.
+    Forceful,
+    /// Ignores this much armor.
+    Piercing(i32),
+    /// Takes both hands to wield.
+    TwoHanded,
+    /// Shoots at enemies a few tiles away.
+    Near,
+    /// Shoots at enemies a long way off.
+    Far,
+}
+
+impl WeaponTag {
+    pub fn name(self) -> String {
+        match self {
+            WeaponTag::Hand => "hand".to_string(),
+            WeaponTag::Close => "close".to_string(),
+            WeaponTag::Reach => "reach".to_string(),
+            WeaponTag::Precise => "precise".to_string(),
+            WeaponTag::Messy => "messy".to_string(),
+            WeaponTag::Forceful => "forceful".to_string(),
+            WeaponTag::Piercing(n) => format!("{} piercing", n),
+            WeaponTag::TwoHanded => "two-handed".to_string(),
+            WeaponTag::Near => "near".to_string(),
+            WeaponTag::Far => "far".to_string(),
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Weapon {
+    pub damage: rltk::DiceType,
+    pub tags: Vec<WeaponTag>,
+    /// What a ranged weapon shoots.
+    pub ammo: Option<AmmoKind>,
+}
+
+/// How far a `Near` ranged weapon shoots.
+pub const NEAR_RANGE: i32 = 5;
+/// How far a `Far` ranged weapon shoots.
+pub const FAR_RANGE: i32 = 9;
+
+impl Weapon {
+    pub fn has(&self, tag: WeaponTag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// How many tiles away this weapon can hit.
+    pub fn range(&self) -> i32 {
+        if self.has(WeaponTag::Far) {
+            FAR_RANGE
+        } else if self.has(WeaponTag::Near) {
+            NEAR_RANGE
+        } else if self.has(WeaponTag::Reach) {
+            2
+        } else {
+            1
+        }
+    }
+
+    pub fn piercing(&self) -> i32 {
+        self.tags
+            .iter()
+            .map(|tag| match tag {
+                WeaponTag::Piercing(n) => *n,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// The weapon `owner` is holding, if any.
+    pub fn wielded_by<'a>(
+        weapons: &'a ReadStorage<Weapon>,
+        equipped: &ReadStorage<Equipped>,
+        owner: Entity,
+    ) -> Option<&'a Weapon> {
+        (weapons, equipped)
+            .join()
+            .find(|(_, equipped_by)| {
+                equipped_by.owner == owner && equipped_by.slot == EquipmentSlot::Melee
+            })
+            .map(|(weapon, _)| weapon)
+    }
+
+    /// The bow, sling or the like `owner` has ready to shoot, if any.
+    pub fn readied_by<'a>(
+        weapons: &'a ReadStorage<Weapon>,
+        equipped: &ReadStorage<Equipped>,
+        owner: Entity,
+    ) -> Option<&'a Weapon> {
+        (weapons, equipped)
+            .join()
+            .find(|(_, equipped_by)| {
+                equipped_by.owner == owner && equipped_by.slot == EquipmentSlot::Ranged
+            })
+            .map(|(weapon, _)| weapon)
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AmmoKind {
+    Arrow,
+    Stone,
+}
+
+impl AmmoKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            AmmoKind::Arrow => "arrows",
+            AmmoKind::Stone => "stones",
+        }
+    }
+}
+
+/// A stack of ammunition. Stacks of the same kind merge when picked up.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Ammo {
+    pub kind: AmmoKind,
+    pub count: i32,
+}
+
+impl Ammo {
+    /// The stack of `kind` that `owner` is carrying, if any.
+    pub fn carried_by(
+        entities: &Entities,
+        ammo: &impl GenericReadStorage<Component = Ammo>,
+        backpack: &impl GenericReadStorage<Component = InBackpack>,
+        owner: Entity,
+        kind: AmmoKind,
+    ) -> Option<Entity> {
+        entities.join().find(|entity| {
+            backpack
+                .get(*entity)
+                .is_some_and(|pack| pack.owner == owner)
+                && ammo.get(*entity).is_some_and(|stack| stack.kind == kind)
+        })
+    }
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct MeleePowerBonus {
     pub bonus: i32,
 }
 
-#[derive(Component, ConvertSaveload, Clone)]
-pub struct ArmorBonus {
-    pub bonus: i32,
+/// World of Dungeons armor: light pieces are worth 1, heavy 2 and a shield 1 more, and the
+/// points of everything worn add up.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ArmorKind {
+    Light,
+    Heavy,
+    Shield,
+}
+
+impl ArmorKind {
+    pub fn default_points(self) -> i32 {
+        match self {
+            ArmorKind::Light => 1,
+            ArmorKind::Heavy => 2,
+            ArmorKind::Shield => 1,
+        }
+    }
+}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct Armor {
+    pub kind: ArmorKind,
+    pub points: i32,
+}
+
+/// The penalty heavy armor gives to DEX and stealth rolls.
+pub const HEAVY_ARMOR_PENALTY: i32 = -1;
+
+impl Armor {
+    /// The armor points of everything `owner` is wearing.
+    pub fn total(
+        armor: &ReadStorage<Armor>,
+        equipped: &ReadStorage<Equipped>,
+        owner: Entity,
+    ) -> i32 {
+        (armor, equipped)
+            .join()
+            .filter(|(_, equipped_by)| equipped_by.owner == owner)
+            .map(|(armor, _)| armor.points)
+            .sum()
+    }
+
+    /// Whether `owner` is wearing any heavy armor.
+    pub fn wears_heavy(
+        armor: &ReadStorage<Armor>,
+        equipped: &ReadStorage<Equipped>,
+        owner: Entity,
+    ) -> bool {
+        (armor, equipped).join().any(|(armor, equipped_by)| {
+            equipped_by.owner == owner && armor.kind == ArmorKind::Heavy
+        })
+    }
+
+    /// `HEAVY_ARMOR_PENALTY` if `owner` is wearing any heavy armor, otherwise 0.
+    pub fn heavy_penalty(
+        armor: &ReadStorage<Armor>,
+        equipped: &ReadStorage<Equipped>,
+        owner: Entity,
+    ) -> i32 {
+        if Armor::wears_heavy(armor, equipped, owner) {
+            HEAVY_ARMOR_PENALTY
+        } else {
+            0
+        }
+    }
 }
 
 // Serialization helper code. We need to implement ConvertSaveLoad for each type that contains an
@@ -169,4 +1055,136 @@ pub struct SerializeMe;
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub seed: u64,
+    pub dungeon: super::dungeon::MasterDungeonMap,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with_one() -> (World, Entity) {
+        let mut world = World::new();
+        world.register::<Armor>();
+        world.register::<Equipped>();
+        world.register::<StatusEffects>();
+        let owner = world.create_entity().build();
+        (world, owner)
+    }
+
+    fn wear(world: &mut World, owner: Entity, kind: ArmorKind, points: i32, slot: EquipmentSlot) {
+        world
+            .create_entity()
+            .with(Armor { kind, points })
+            .with(Equipped { owner, slot })
+            .build();
+    }
+
+    #[test]
+    fn armor_adds_up_across_slots() {
+        let (mut world, owner) = world_with_one();
+        wear(&mut world, owner, ArmorKind::Light, 1, EquipmentSlot::Head);
+        wear(&mut world, owner, ArmorKind::Heavy, 2, EquipmentSlot::Body);
+        wear(
+            &mut world,
+            owner,
+            ArmorKind::Shield,
+            1,
+            EquipmentSlot::OffHand,
+        );
+        let someone_else = world.create_entity().build();
+        wear(
+            &mut world,
+            someone_else,
+            ArmorKind::Heavy,
+            3,
+            EquipmentSlot::Body,
+        );
+        // Armor lying around unworn counts for nobody
+        world
+            .create_entity()
+            .with(Armor {
+                kind: ArmorKind::Heavy,
+                points: 3,
+            })
+            .build();
+
+        let (armor, equipped) = (world.read_storage(), world.read_storage());
+        assert_eq!(Armor::total(&armor, &equipped, owner), 4);
+        assert_eq!(Armor::total(&armor, &equipped, someone_else), 3);
+    }
+
+    #[test]
+    fn only_heavy_armor_slows_you_down() {
+        let (mut world, owner) = world_with_one();
+        wear(&mut world, owner, ArmorKind::Light, 1, EquipmentSlot::Body);
+        wear(
+            &mut world,
+            owner,
+            ArmorKind::Shield,
+            1,
+            EquipmentSlot::OffHand,
+        );
+        {
+            let (armor, equipped) = (world.read_storage(), world.read_storage());
+            assert_eq!(Armor::heavy_penalty(&armor, &equipped, owner), 0);
+        }
+        wear(&mut world, owner, ArmorKind::Heavy, 2, EquipmentSlot::Head);
+        let (armor, equipped) = (world.read_storage(), world.read_storage());
+        assert_eq!(
+            Armor::heavy_penalty(&armor, &equipped, owner),
+            HEAVY_ARMOR_PENALTY
+        );
+    }
+
+    #[test]
+    fn statuses_that_stack_add_up() {
+        let (world, entity) = world_with_one();
+        let mut store = world.write_storage::<StatusEffects>();
+        StatusEffects::add(&mut store, entity, Status::Poisoned, 3);
+        StatusEffects::add(&mut store, entity, Status::Poisoned, 2);
+        let statuses = store.get(entity).unwrap();
+        assert_eq!(statuses.turns(Status::Poisoned), Some(5));
+        assert_eq!(statuses.effects.len(), 1);
+    }
+
+    #[test]
+    fn other_statuses_refresh_to_the_longer() {
+        let (world, entity) = world_with_one();
+        let mut store = world.write_storage::<StatusEffects>();
+        StatusEffects::add(&mut store, entity, Status::Confused, 4);
+        StatusEffects::add(&mut store, entity, Status::Confused, 2);
+        assert_eq!(store.get(entity).unwrap().turns(Status::Confused), Some(4));
+        StatusEffects::add(&mut store, entity, Status::Confused, 6);
+        assert_eq!(store.get(entity).unwrap().turns(Status::Confused), Some(6));
+    }
+
+    #[test]
+    fn different_statuses_sit_side_by_side() {
+        let (world, entity) = world_with_one();
+        let mut store = world.write_storage::<StatusEffects>();
+        StatusEffects::add(&mut store, entity, Status::Poisoned, 3);
+        StatusEffects::add(&mut store, entity, Status::Stunned, 1);
+        let statuses = store.get(entity).unwrap();
+        assert_eq!(statuses.turns(Status::Poisoned), Some(3));
+        assert_eq!(statuses.turns(Status::Stunned), Some(1));
+        assert!(statuses.helpless());
+    }
+
+    #[test]
+    fn the_highest_initiative_goes_first_and_the_player_wins_ties() {
+        let mut world = World::new();
+        world.register::<Initiative>();
+        let mut roll = |roll: i32| world.create_entity().with(Initiative { roll }).build();
+        let goblin = roll(8);
+        let orc = roll(12);
+        let rat = roll(5);
+        let player = roll(8);
+        let bat = roll(8);
+        // Not in the encounter
+        world.create_entity().build();
+
+        let order = Initiative::turn_order(&world.entities(), &world.read_storage(), player);
+        assert_eq!(order, vec![orc, player, goblin, bat, rat]);
+    }
 }
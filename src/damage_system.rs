@@ -1,38 +1,94 @@
-use super::{gamelog::GameLog, CombatStats, Name, Player, RunState, SufferDamage};
+use super::{
+    gamelog::GameLog, gui, party_system, CombatStats, Experience, Faction, KnownSpell, Name,
+    PartyMember, Reputation, RunState, Status, StatusEffects, SufferDamage,
+};
 use specs::prelude::*;
 
+/// Applies the turn's damage. Whoever in the party finishes off a monster earns experience for
+/// it, more for tougher ones, and the whole party loses standing with the faction of everyone they
+/// hurt. Getting hurt wakes up anyone who was asleep.
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
+        Entities<'a>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Experience>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, Faction>,
+        WriteStorage<'a, Reputation>,
+        ReadStorage<'a, PartyMember>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (
+            entities,
+            mut stats,
+            mut damage,
+            mut experience,
+            mut status_effects,
+            factions,
+            mut reputations,
+            members,
+        ) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        // Experience earned, by party slot
+        let mut xp_gained: Vec<(usize, i32)> = Vec::new();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            for (amount, dealt_by) in damage.amount.iter() {
+                let was_alive = stats.hp > 0;
+                stats.hp -= amount;
+                let killed = was_alive && stats.hp < 1 && members.get(entity).is_none();
+                if let (true, Some(slot)) = (killed, dealt_by) {
+                    xp_gained.push((*slot, stats.max_hp / 5 + 1));
+                }
+            }
+            let provoked = damage
+                .amount
+                .iter()
+                .filter(|(amount, dealt_by)| *amount > 0 && dealt_by.is_some())
+                .count() as i32;
+            if let (true, Some(faction)) = (provoked > 0, factions.get(entity)) {
+                for (member, _) in (&entities, &members).join() {
+                    Reputation::adjust(&mut reputations, member, &faction.name, -provoked);
+                }
+            }
+            if damage.amount.iter().any(|(amount, _)| *amount > 0) {
+                if let Some(statuses) = status_effects.get_mut(entity) {
+                    statuses.effects.retain(|e| e.status != Status::Sleeping);
+                }
+            }
+        }
+        for (member, party) in (&entities, &members).join() {
+            let xp: i32 = xp_gained
+                .iter()
+                .filter(|(slot, _)| *slot == party.slot)
+                .map(|(_, xp)| xp)
+                .sum();
+            Experience::mark(&mut experience, member, xp);
         }
 
         damage.clear();
     }
 }
 
+/// Clears away the dead. One of the party going down isn't the end of them: they take their Last
+/// Breath, under the player's control.
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut dying: Option<Entity> = None;
     // Using a scope to make the borrow checker happy
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
-        let players = ecs.read_storage::<Player>();
+        let members = ecs.read_storage::<PartyMember>();
         let names = ecs.read_storage::<Name>();
         let entities = ecs.entities();
         let mut log = ecs.write_resource::<GameLog>();
         for (entity, stats) in (&entities, &combat_stats).join() {
             if stats.hp < 1 {
-                let player = players.get(entity);
-                match player {
+                let member = members.get(entity);
+                match member {
                     None => {
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
@@ -41,15 +97,31 @@ pub fn delete_the_dead(ecs: &mut World) {
                         dead.push(entity)
                     }
                     Some(_) => {
+                        // Death's door, rather than straight through it
                         let mut runstate = ecs.write_resource::<RunState>();
-                        *runstate = RunState::GameOver;
+                        if !matches!(*runstate, RunState::LastBreath { .. } | RunState::GameOver) {
+                            *runstate = RunState::LastBreath {
+                                step: gui::LastBreathStep::Roll,
+                            };
+                            dying = Some(entity);
+                        }
                     }
                 }
             }
         }
     }
 
-    for victim in dead {
+    if let Some(dying) = dying {
+        party_system::take_control(ecs, dying);
+    }
+
+    // Whatever spells they knew die with them
+    let spells: Vec<Entity> = (&ecs.entities(), &ecs.read_storage::<KnownSpell>())
+        .join()
+        .filter(|(_, known)| dead.contains(&known.caster))
+        .map(|(spell, _)| spell)
+        .collect();
+    for victim in dead.into_iter().chain(spells) {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
 }
@@ -0,0 +1,150 @@
+use super::{Map, TileType};
+use rltk::Point;
+use specs::prelude::*;
+use std::collections::VecDeque;
+
+/// Flee maps are the approach map turned upside down and scaled by this, so that running from
+/// the player prefers a distant escape over the nearest corner.
+const FLEE_SCALE: f32 = -1.2;
+
+/// Shared distance maps for everyone to steer by. Each holds, for every tile, how far it is
+/// from what the map is about; anything out of reach is `f32::MAX`. Walking downhill leads
+/// there, so nobody needs a path search of their own.
+#[derive(Default)]
+pub struct DijkstraMaps {
+    /// Distance to the player.
+    pub approach: Vec<f32>,
+    /// Downhill runs away from the player, towards wherever is furthest from them.
+    pub flee: Vec<f32>,
+    /// Distance, over what the player has seen, to the nearest place they haven't.
+    pub explore: Vec<f32>,
+    /// Distance, over what the player has seen, to the stairs down if they've found them.
+    pub stairs: Vec<f32>,
+    /// What the maps were last built from: the depth, the player's tile, how much of the map
+    /// had been revealed and how many doors were closed.
+    built_for: Option<(i32, usize, usize, usize)>,
+}
+
+impl DijkstraMaps {
+    /// Where to step from `idx` to get further downhill on `values`, if anywhere. Closed doors
+    /// count, since they can be opened; tiles with someone in them don't.
+    pub fn downhill(map: &Map, values: &[f32], idx: usize) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for exit in terrain_exits(map, idx, false) {
+            let value = values[exit];
+            if value < best.map_or(values[idx], |(_, v)| v)
+                && (!map.blocked[exit] || map.closed_doors.contains(&exit))
+            {
+                best = Some((exit, value));
+            }
+        }
+        best.map(|(exit, _)| exit)
+    }
+}
+
+/// The tiles next to `idx` that could be walked onto, ignoring whoever's standing there; with
+/// `revealed_only`, just the ones the player has seen.
+fn terrain_exits(map: &Map, idx: usize, revealed_only: bool) -> Vec<usize> {
+    let x = idx as i32 % map.width;
+    let y = idx as i32 / map.width;
+    let mut exits = Vec::new();
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let (nx, ny) = (x + dx, y + dy);
+            if (dx == 0 && dy == 0) || nx < 1 || nx > map.width - 1 || ny < 1 || ny > map.height - 1
+            {
+                continue;
+            }
+            let exit = map.xy_idx(nx, ny);
+            // Anything else blocking the view is a locked door
+            let walkable = map.tiles[exit] != TileType::Wall
+                && (!map.view_blocked.contains(&exit) || map.closed_doors.contains(&exit));
+            if walkable && (!revealed_only || map.revealed_tiles[exit]) {
+                exits.push(exit);
+            }
+        }
+    }
+    exits
+}
+
+/// Spreads the lowest values in `values` out across the map, a step costing 1 (or 1.45 on the
+/// diagonal), until nothing can get any lower.
+fn flood(map: &Map, values: &mut [f32], revealed_only: bool) {
+    let mut open: VecDeque<usize> = (0..values.len())
+        .filter(|idx| values[*idx] < f32::MAX)
+        .collect();
+    let w = map.width as usize;
+    while let Some(idx) = open.pop_front() {
+        for exit in terrain_exits(map, idx, revealed_only) {
+            let diagonal = exit % w != idx % w && exit / w != idx / w;
+            let cost = if diagonal { 1.45 } else { 1.0 };
+            if values[idx] + cost < values[exit] {
+                values[exit] = values[idx] + cost;
+                open.push_back(exit);
+            }
+        }
+    }
+}
+
+/// Rebuilds the `DijkstraMaps`, but only when the map or the player's position has changed
+/// since they were last built.
+pub struct DijkstraMapSystem {}
+
+impl<'a> System<'a> for DijkstraMapSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>,
+        WriteExpect<'a, DijkstraMaps>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, mut maps) = data;
+
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        let revealed = map.revealed_tiles.iter().filter(|r| **r).count();
+        let key = (map.depth, player_idx, revealed, map.closed_doors.len());
+        if maps.built_for == Some(key) {
+            return;
+        }
+        maps.built_for = Some(key);
+
+        let size = map.tiles.len();
+        let mut approach = vec![f32::MAX; size];
+        approach[player_idx] = 0.0;
+        flood(&map, &mut approach, false);
+
+        let mut flee: Vec<f32> = approach
+            .iter()
+            .map(|v| {
+                if *v < f32::MAX {
+                    v * FLEE_SCALE
+                } else {
+                    f32::MAX
+                }
+            })
+            .collect();
+        flood(&map, &mut flee, false);
+
+        // The edge of the known map: unseen tiles beside seen ones
+        let mut explore = vec![f32::MAX; size];
+        for (idx, value) in explore.iter_mut().enumerate() {
+            if !map.revealed_tiles[idx] && !terrain_exits(&map, idx, true).is_empty() {
+                *value = 0.0;
+            }
+        }
+        flood(&map, &mut explore, true);
+
+        let mut stairs = vec![f32::MAX; size];
+        for (idx, value) in stairs.iter_mut().enumerate() {
+            if map.revealed_tiles[idx] && map.tiles[idx] == TileType::DownStairs {
+                *value = 0.0;
+            }
+        }
+        flood(&map, &mut stairs, true);
+
+        maps.approach = approach;
+        maps.flee = flee;
+        maps.explore = explore;
+        maps.stairs = stairs;
+    }
+}
@@ -0,0 +1,90 @@
+use super::{Map, OtherLevelPosition, PartyMember, Position, Viewshed};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+/// Every level the player has visited, keyed by depth. Entities that live on those levels stay
+/// in the ECS with an `OtherLevelPosition` instead of a `Position` until the player returns.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct MasterDungeonMap {
+    maps: HashMap<i32, Map>,
+}
+
+impl MasterDungeonMap {
+    pub fn new() -> MasterDungeonMap {
+        MasterDungeonMap {
+            maps: HashMap::new(),
+        }
+    }
+
+    pub fn store_map(&mut self, map: &Map) {
+        self.maps.insert(map.depth, map.clone());
+    }
+
+    pub fn get_map(&self, depth: i32) -> Option<Map> {
+        if let Some(map) = self.maps.get(&depth) {
+            let mut result = map.clone();
+            result.tile_content = vec![Vec::new(); result.tiles.len()];
+            for visible in result.visible_tiles.iter_mut() {
+                *visible = false;
+            }
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+/// Takes everything that isn't in the party off the current level, remembering where it was.
+pub fn freeze_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let members = ecs.read_storage::<PartyMember>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let mut pos_to_delete: Vec<Entity> = Vec::new();
+    for (entity, pos, _) in (&entities, &positions, !&members).join() {
+        other_level_positions
+            .insert(
+                entity,
+                OtherLevelPosition {
+                    x: pos.x,
+                    y: pos.y,
+                    depth,
+                },
+            )
+            .expect("Insert fail");
+        pos_to_delete.push(entity);
+    }
+
+    for p in pos_to_delete.iter() {
+        positions.remove(*p);
+    }
+}
+
+/// Puts back everything that was left on the current level when the player last visited it.
+pub fn thaw_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let mut pos_to_delete: Vec<Entity> = Vec::new();
+    for (entity, pos) in (&entities, &other_level_positions).join() {
+        if pos.depth == depth {
+            positions
+                .insert(entity, Position { x: pos.x, y: pos.y })
+                .expect("Insert fail");
+            if let Some(viewshed) = viewsheds.get_mut(entity) {
+                viewshed.dirty = true;
+            }
+            pos_to_delete.push(entity);
+        }
+    }
+
+    for p in pos_to_delete.iter() {
+        other_level_positions.remove(*p);
+    }
+}
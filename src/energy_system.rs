@@ -0,0 +1,243 @@
+use super::{Armor, CombatStats, Energy, Equipped, InBackpack, Sneaking, Status, StatusEffects};
+use specs::prelude::*;
+
+/// How many things anyone can carry without slowing down, before STR.
+pub const CARRY_CAPACITY: i32 = 10;
+/// Each point of STR carries this many more.
+pub const CARRY_PER_STR: i32 = 2;
+/// The speed lost for each thing carried over capacity, and for wearing heavy armor.
+pub const ENCUMBRANCE_PENALTY: i32 = 10;
+/// Nothing is ever slower than this.
+pub const MIN_SPEED: i32 = 25;
+
+/// The speed `entity` loses to heavy armor and to carrying more than it can manage.
+pub fn encumbrance(
+    entity: Entity,
+    combat_stats: &ReadStorage<CombatStats>,
+    armor: &ReadStorage<Armor>,
+    equipped: &ReadStorage<Equipped>,
+    backpack: &ReadStorage<InBackpack>,
+) -> i32 {
+    let mut penalty = 0;
+    if Armor::wears_heavy(armor, equipped, entity) {
+        penalty += ENCUMBRANCE_PENALTY;
+    }
+    let carried = backpack.join().filter(|pack| pack.owner == entity).count() as i32;
+    let capacity =
+        CARRY_CAPACITY + CARRY_PER_STR * combat_stats.get(entity).map_or(0, |stats| stats.str);
+    penalty + ENCUMBRANCE_PENALTY * i32::max(0, carried - capacity)
+}
+
+/// The energy an entity gains a turn: its own speed, less its encumbrance, then half as much
+/// again when Hasted or halved when Slowed or sneaking.
+pub fn current_speed(
+    energy: &Energy,
+    encumbrance: i32,
+    statuses: Option<&StatusEffects>,
+    sneaking: bool,
+) -> i32 {
+    let mut speed = i32::max(MIN_SPEED, energy.speed - encumbrance);
+    if let Some(statuses) = statuses {
+        if statuses.has(Status::Hasted) {
+            speed += speed / 2;
+        }
+        if statuses.has(Status::Slowed) {
+            speed /= 2;
+        }
+    }
+    if sneaking {
+        speed /= 2;
+    }
+    speed
+}
+
+/// A turn passes: everyone gains their current speed in energy. Nobody can bank more than a
+/// turn's worth by waiting around.
+pub struct EnergySystem {}
+
+impl<'a> System<'a> for EnergySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Energy>,
+        ReadStorage<'a, StatusEffects>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Armor>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Sneaking>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut energy,
+            status_effects,
+            combat_stats,
+            armor,
+            equipped,
+            backpack,
+            sneaking,
+        ) = data;
+
+        for (entity, energy) in (&entities, &mut energy).join() {
+            let encumbrance = encumbrance(entity, &combat_stats, &armor, &equipped, &backpack);
+            let speed = current_speed(
+                energy,
+                encumbrance,
+                status_effects.get(entity),
+                sneaking.get(entity).is_some(),
+            );
+            energy.energy = i32::min(energy.energy + speed, speed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ArmorKind, EquipmentSlot, StatusEffect, ACTION_COST, NORMAL_SPEED};
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::new();
+        System::setup(&mut EnergySystem {}, &mut world);
+        world
+    }
+
+    fn someone(world: &mut World, speed: i32, str: i32) -> Entity {
+        world
+            .create_entity()
+            .with(Energy::new(speed))
+            .with(CombatStats {
+                str,
+                dex: 0,
+                con: 0,
+                int: 0,
+                wis: 0,
+                cha: 0,
+                max_hp: 10,
+                hp: 10,
+            })
+            .build()
+    }
+
+    fn speed_of(world: &World, entity: Entity) -> i32 {
+        let encumbrance = encumbrance(
+            entity,
+            &world.read_storage(),
+            &world.read_storage(),
+            &world.read_storage(),
+            &world.read_storage(),
+        );
+        current_speed(
+            world.read_storage::<Energy>().get(entity).unwrap(),
+            encumbrance,
+            world.read_storage::<StatusEffects>().get(entity),
+            world.read_storage::<Sneaking>().contains(entity),
+        )
+    }
+
+    fn afflict(world: &mut World, entity: Entity, status: Status) {
+        world
+            .write_storage::<StatusEffects>()
+            .insert(
+                entity,
+                StatusEffects {
+                    effects: vec![StatusEffect { status, turns: 5 }],
+                },
+            )
+            .expect("Unable to insert status");
+    }
+
+    #[test]
+    fn haste_slow_and_sneaking_change_speed_by_half() {
+        let mut world = world();
+        let normal = someone(&mut world, NORMAL_SPEED, 0);
+        let hasted = someone(&mut world, NORMAL_SPEED, 0);
+        let slowed = someone(&mut world, NORMAL_SPEED, 0);
+        afflict(&mut world, hasted, Status::Hasted);
+        afflict(&mut world, slowed, Status::Slowed);
+        assert_eq!(speed_of(&world, normal), 100);
+        assert_eq!(speed_of(&world, hasted), 150);
+        assert_eq!(speed_of(&world, slowed), 50);
+        world
+            .write_storage::<Sneaking>()
+            .insert(hasted, Sneaking {})
+            .expect("Unable to insert sneaking");
+        assert_eq!(speed_of(&world, hasted), 75);
+    }
+
+    #[test]
+    fn heavy_armor_and_a_full_pack_slow_you_down() {
+        let mut world = world();
+        let armored = someone(&mut world, NORMAL_SPEED, 0);
+        world
+            .create_entity()
+            .with(Armor {
+                kind: ArmorKind::Heavy,
+                points: 2,
+            })
+            .with(Equipped {
+                owner: armored,
+                slot: EquipmentSlot::Body,
+            })
+            .build();
+        assert_eq!(
+            speed_of(&world, armored),
+            NORMAL_SPEED - ENCUMBRANCE_PENALTY
+        );
+
+        let weak = someone(&mut world, NORMAL_SPEED, 0);
+        let strong = someone(&mut world, NORMAL_SPEED, 1);
+        for owner in [weak, strong] {
+            for _ in 0..CARRY_CAPACITY + 2 {
+                world.create_entity().with(InBackpack { owner }).build();
+            }
+        }
+        assert_eq!(
+            speed_of(&world, weak),
+            NORMAL_SPEED - 2 * ENCUMBRANCE_PENALTY
+        );
+        assert_eq!(speed_of(&world, strong), NORMAL_SPEED);
+    }
+
+    #[test]
+    fn nothing_is_slower_than_the_minimum_except_the_slowed() {
+        let mut world = world();
+        let crawler = someone(&mut world, MIN_SPEED, 0);
+        for _ in 0..CARRY_CAPACITY + 5 {
+            world
+                .create_entity()
+                .with(InBackpack { owner: crawler })
+                .build();
+        }
+        assert_eq!(speed_of(&world, crawler), MIN_SPEED);
+        afflict(&mut world, crawler, Status::Slowed);
+        assert_eq!(speed_of(&world, crawler), MIN_SPEED / 2);
+    }
+
+    #[test]
+    fn the_fast_act_more_often_than_the_slow() {
+        let mut world = world();
+        let speeds = [50, 100, 200];
+        let entities: Vec<Entity> = speeds
+            .iter()
+            .map(|speed| someone(&mut world, *speed, 0))
+            .collect();
+        let mut actions = [0; 3];
+        for _ in 0..10 {
+            {
+                let mut energy = world.write_storage::<Energy>();
+                for (i, entity) in entities.iter().enumerate() {
+                    while Energy::ready(&energy, *entity) {
+                        Energy::spend(&mut energy, *entity, ACTION_COST);
+                        actions[i] += 1;
+                    }
+                }
+            }
+            EnergySystem {}.run_now(&world);
+        }
+        // Nobody starts with energy banked, so the fast only act twice from the second turn
+        assert_eq!(actions, [5, 10, 19]);
+    }
+}
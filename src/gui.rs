@@ -1,6 +1,18 @@
 use super::{
-    gamelog::GameLog, CombatStats, Equipped, InBackpack, Map, Name, Player, Position, RunState,
-    State, Viewshed,
+    camera,
+    character::{
+        stat_values, Ability, CharacterClass, CharacterDraft, PartyDraft, Skill, MAX_PARTY_SIZE,
+        MAX_STAT, SKILL_CHOICES, STAT_ARRAY, STAT_NAMES,
+    },
+    energy_system::{current_speed, encumbrance},
+    gamelog::GameLog,
+    last_breath::{self, Bargain},
+    raws::RawMaster,
+    roll::Outcome,
+    Abilities, AiState, Ammo, Armor, Class, CombatStats, Debilities, Debility, Energy, Equippable,
+    Equipped, Experience, Faction, Hidden, InBackpack, Initiative, Map, Name, PartyMember, Player,
+    Position, Reaction, Renderable, Reputation, RunState, Skills, Sneaking, State, StatusEffects,
+    Viewshed,
 };
 use rltk::{Point, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
@@ -16,7 +28,18 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     );
 
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let experience = ecs.read_storage::<Experience>();
     let players = ecs.read_storage::<Player>();
+    for (_player, exp) in (&players, &experience).join() {
+        let level = format!(" Lvl {} XP {}/{} ", exp.level, exp.xp, exp.next_level());
+        ctx.print_color(
+            28,
+            43,
+            RGB::named(rltk::CYAN),
+            RGB::named(rltk::BLACK),
+            &level,
+        );
+    }
     for (_player, stats) in (&players, &combat_stats).join() {
         let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
         ctx.print_color(
@@ -28,9 +51,9 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
 
         ctx.draw_bar_horizontal(
-            28,
+            44,
             43,
-            51,
+            35,
             stats.hp,
             stats.max_hp,
             RGB::named(rltk::RED),
@@ -48,14 +71,45 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         &depth,
     );
 
-    let log = ecs.fetch::<GameLog>();
-    let mut y = 44;
-    for s in log.entries.iter().rev() {
-        if y < 49 {
-            ctx.print(2, y, s);
+    // Whatever the player is suffering from (or enjoying) runs along the bottom edge
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    for (_player, statuses) in (&players, &status_effects).join() {
+        let mut x = 2;
+        for effect in statuses.effects.iter() {
+            let label = format!(" {} {} ", effect.status.name(), effect.turns);
+            let colour = if effect.status.harmful() {
+                RGB::named(rltk::ORANGE)
+            } else {
+                RGB::named(rltk::GREEN)
+            };
+            ctx.print_color(x, 49, colour, RGB::named(rltk::BLACK), &label);
+            x += label.len() as i32;
         }
-        y += 1;
     }
+    // ...with debilities, which last until cured, over on the right
+    let debilities = ecs.read_storage::<Debilities>();
+    for (_player, debilities) in (&players, &debilities).join() {
+        let names: Vec<&str> = debilities.debilities.iter().map(|d| d.name()).collect();
+        if names.is_empty() {
+            continue;
+        }
+        let label = format!(" {} ", names.join(" "));
+        ctx.print_color(
+            78 - label.len() as i32,
+            49,
+            RGB::named(rltk::RED),
+            RGB::named(rltk::BLACK),
+            &label,
+        );
+    }
+
+    let log = ecs.fetch::<GameLog>();
+    for (y, s) in (44..49).zip(log.entries.iter().rev()) {
+        ctx.print(2, y, s);
+    }
+
+    draw_party(ecs, ctx);
+    draw_turn_order(ecs, ctx);
 
     // Draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
@@ -63,20 +117,173 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
+/// The roster at the top left of the map: everyone in the party in marching order, each in their
+/// own colour, with their hit points. Whoever is under control is marked.
+fn draw_party(ecs: &World, ctx: &mut Rltk) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let members = ecs.read_storage::<PartyMember>();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let mut party: Vec<(Entity, &PartyMember)> = (&ecs.entities(), &members).join().collect();
+    party.sort_by_key(|(_, member)| member.slot);
+
+    let width = 26;
+    ctx.draw_box(
+        0,
+        0,
+        width,
+        party.len() as i32 + 1,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        2,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Party",
+    );
+    for (i, (entity, _)) in party.iter().enumerate() {
+        let y = 1 + i as i32;
+        let (hp, max_hp) = combat_stats
+            .get(*entity)
+            .map_or((0, 0), |stats| (stats.hp, stats.max_hp));
+        let colour = if hp < 1 {
+            RGB::named(rltk::RED)
+        } else if *entity == player_entity {
+            RGB::named(rltk::YELLOW)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        if *entity == player_entity {
+            ctx.print_color(1, y, colour, RGB::named(rltk::BLACK), ">");
+        }
+        if let Some(render) = renderables.get(*entity) {
+            ctx.set(2, y, render.fg, RGB::named(rltk::BLACK), render.glyph);
+        }
+        let mut name = names
+            .get(*entity)
+            .map_or("Someone".to_string(), |n| n.name.clone());
+        name.truncate(13);
+        let label = format!("{:<13} {:>3}/{:<3}", name, hp, max_hp);
+        ctx.print_color(4, y, colour, RGB::named(rltk::BLACK), &label);
+    }
+}
+
+/// How many of the turn order fit down the side of the map.
+const TURN_ORDER_ROWS: usize = 20;
+
+/// While an encounter lasts, shows who acts in what order (with their initiative) at the top right
+/// of the map, with the rest of the party picked out in cyan. Anyone the player can't see right
+/// now is only "Something".
+fn draw_turn_order(ecs: &World, ctx: &mut Rltk) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let initiative = ecs.read_storage::<Initiative>();
+    let order = Initiative::turn_order(&ecs.entities(), &initiative, player_entity);
+    if order.is_empty() {
+        return;
+    }
+    let map = ecs.fetch::<Map>();
+    let names = ecs.read_storage::<Name>();
+    let positions = ecs.read_storage::<Position>();
+    let members = ecs.read_storage::<PartyMember>();
+
+    let rows = usize::min(order.len(), TURN_ORDER_ROWS);
+    let width = 22;
+    let x = camera::VIEWPORT_WIDTH - width - 1;
+    ctx.draw_box(
+        x,
+        0,
+        width,
+        rows as i32 + 1,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        x + 2,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Turn Order",
+    );
+    for (i, entity) in order.iter().take(rows).enumerate() {
+        let seen = positions
+            .get(*entity)
+            .is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+        let (name, colour) = if *entity == player_entity {
+            ("You", RGB::named(rltk::YELLOW))
+        } else if members.get(*entity).is_some() {
+            (
+                names.get(*entity).map_or("Someone", |n| n.name.as_str()),
+                RGB::named(rltk::CYAN),
+            )
+        } else if seen {
+            (
+                names.get(*entity).map_or("Someone", |n| n.name.as_str()),
+                RGB::named(rltk::WHITE),
+            )
+        } else {
+            ("Something", RGB::named(rltk::GREY))
+        };
+        let roll = initiative.get(*entity).map_or(0, |i| i.roll);
+        let mut label = format!("{:>3} {}", roll, name);
+        label.truncate(width as usize - 1);
+        ctx.print_color(x + 1, 1 + i as i32, colour, RGB::named(rltk::BLACK), &label);
+    }
+}
+
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    let ai_states = ecs.read_storage::<AiState>();
+    let factions = ecs.read_storage::<Faction>();
+    let reputations = ecs.read_storage::<Reputation>();
+    let raws = ecs.fetch::<RawMaster>();
+    let player_entity = ecs.fetch::<Entity>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
+    if mouse_pos.0 < 0
+        || mouse_pos.0 >= camera::VIEWPORT_WIDTH
+        || mouse_pos.1 < 0
+        || mouse_pos.1 >= camera::VIEWPORT_HEIGHT
+    {
+        return;
+    }
+    let mouse_map_pos = camera::screen_to_map(ecs, mouse_pos);
+    if mouse_map_pos.x < 0
+        || mouse_map_pos.x >= map.width
+        || mouse_map_pos.y < 0
+        || mouse_map_pos.y >= map.height
+    {
         return;
     }
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
+    for (entity, name, position, _hidden) in (&ecs.entities(), &names, &positions, !&hidden).join()
+    {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
-            tooltip.push(name.name.to_string());
+        if position.x == mouse_map_pos.x && position.y == mouse_map_pos.y && map.visible_tiles[idx]
+        {
+            let mut notes: Vec<&str> = Vec::new();
+            if let Some(state) = ai_states.get(entity) {
+                notes.push(state.name());
+                match Faction::reaction(&raws, &factions, &reputations, entity, *player_entity) {
+                    Reaction::Attack => {}
+                    Reaction::Ignore => notes.push("neutral"),
+                    Reaction::Flee => notes.push("wary"),
+                }
+            }
+            if let Some(statuses) = status_effects.get(entity) {
+                notes.extend(statuses.effects.iter().map(|e| e.status.name()));
+            }
+            if notes.is_empty() {
+                tooltip.push(name.name.to_string());
+            } else {
+                tooltip.push(format!("{} ({})", name.name, notes.join(", ")));
+            }
         }
     }
 
@@ -92,8 +299,7 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         if mouse_pos.0 > 40 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                 ctx.print_color(
                     left_x,
                     y,
@@ -108,23 +314,21 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"->".to_string(),
+                "->",
             );
         } else {
             let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
             let left_x = mouse_pos.0 + 3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                 ctx.print_color(
                     left_x + 1,
                     y,
@@ -139,17 +343,16 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"<-".to_string(),
+                "<-",
             );
         }
     }
@@ -166,6 +369,8 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let equippable_items = gs.ecs.read_storage::<Equippable>();
+    let ammo = gs.ecs.read_storage::<Ammo>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -173,11 +378,11 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         .filter(|item| item.0.owner == *player_entity);
     let count = inventory.count();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
-        31,
+        41,
         (count + 3) as i32,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
@@ -203,6 +408,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         .filter(|item| item.1.owner == *player_entity)
         .enumerate()
     {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -225,9 +431,12 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        match (equippable_items.get(entity), ammo.get(entity)) {
+            (Some(item), _) => ctx.print(21, y, format!("{} ({})", name.name, item.slot.name())),
+            (None, Some(stack)) => ctx.print(21, y, format!("{} ({})", name.name, stack.count)),
+            (None, None) => ctx.print(21, y, &name.name),
+        }
         equippable.push(entity);
-        y += 1;
     }
 
     match ctx.key {
@@ -248,10 +457,157 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     }
 }
 
+/// The player's stats, speed, debilities, skills and abilities, over the map.
+pub fn show_character(gs: &mut State, ctx: &mut Rltk) -> ItemMenuResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let classes = gs.ecs.read_storage::<Class>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let experience = gs.ecs.read_storage::<Experience>();
+    let skills = gs.ecs.read_storage::<Skills>();
+    let abilities = gs.ecs.read_storage::<Abilities>();
+    let debilities = gs.ecs.read_storage::<Debilities>();
+    let status_effects = gs.ecs.read_storage::<StatusEffects>();
+    let energy = gs.ecs.read_storage::<Energy>();
+    let armor = gs.ecs.read_storage::<Armor>();
+    let equipped = gs.ecs.read_storage::<Equipped>();
+    let backpack = gs.ecs.read_storage::<InBackpack>();
+    let sneaking = gs.ecs.read_storage::<Sneaking>();
+    let reputations = gs.ecs.read_storage::<Reputation>();
+
+    let x = 7;
+    ctx.draw_box(
+        x - 2,
+        8,
+        70,
+        30,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        x + 1,
+        8,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Character",
+    );
+    ctx.print_color(
+        x + 1,
+        38,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to close",
+    );
+
+    let name = names
+        .get(*player_entity)
+        .map_or("", |n| n.name.as_str())
+        .to_string();
+    let class = classes.get(*player_entity).map_or("", |c| c.class.name());
+    let level = experience.get(*player_entity).map_or(1, |exp| exp.level);
+    ctx.print_color(
+        x,
+        10,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        format!("{} the level {} {}", name, level, class),
+    );
+
+    if let Some(stats) = combat_stats.get(*player_entity) {
+        ctx.print(x, 12, format!("HP  {} / {}", stats.hp, stats.max_hp));
+        if let Some(energy) = energy.get(*player_entity) {
+            let encumbrance =
+                encumbrance(*player_entity, &combat_stats, &armor, &equipped, &backpack);
+            let speed = current_speed(
+                energy,
+                encumbrance,
+                status_effects.get(*player_entity),
+                sneaking.get(*player_entity).is_some(),
+            );
+            let note = if sneaking.get(*player_entity).is_some() {
+                " (sneaking)"
+            } else {
+                ""
+            };
+            ctx.print(x + 16, 12, format!("Speed {}{}", speed, note));
+        }
+        for (i, value) in stat_values(stats).iter().enumerate() {
+            let y = 13 + i as i32;
+            ctx.print(x, y, format!("{} {:+}", STAT_NAMES[i], value));
+            let debility = Debility::ALL[i];
+            if debilities
+                .get(*player_entity)
+                .is_some_and(|d| d.debilities.contains(&debility))
+            {
+                ctx.print_color(
+                    x + 8,
+                    y,
+                    RGB::named(rltk::RED),
+                    RGB::named(rltk::BLACK),
+                    format!("{} (-1)", debility.name()),
+                );
+            }
+        }
+    }
+
+    ctx.print(x, 20, "Skills:");
+    if let Some(skills) = skills.get(*player_entity) {
+        for (i, skill) in skills.skills.iter().enumerate() {
+            ctx.print(x + 2, 21 + i as i32, skill.name());
+        }
+    }
+
+    ctx.print(x, 24, "Abilities:");
+    if let Some(abilities) = abilities.get(*player_entity) {
+        for (i, ability) in abilities.abilities.iter().enumerate() {
+            ctx.print(
+                x + 2,
+                25 + i as i32,
+                format!("{}: {}", ability.name(), ability.description()),
+            );
+        }
+    }
+
+    ctx.print(x, 30, "Conditions:");
+    if let Some(statuses) = status_effects.get(*player_entity) {
+        for (i, effect) in statuses.effects.iter().enumerate() {
+            ctx.print(
+                x + 2,
+                31 + i as i32,
+                format!("{} ({} turns)", effect.status.name(), effect.turns),
+            );
+        }
+    }
+
+    ctx.print(x + 34, 30, "Reputation:");
+    if let Some(reputation) = reputations.get(*player_entity) {
+        let mut standings: Vec<(&String, &i32)> = reputation
+            .standing
+            .iter()
+            .filter(|(_, standing)| **standing != 0)
+            .collect();
+        standings.sort();
+        for (i, (faction, standing)) in standings.iter().enumerate() {
+            let note = if **standing < 0 { " (hostile)" } else { "" };
+            ctx.print(
+                x + 36,
+                31 + i as i32,
+                format!("{} {:+}{}", faction, standing, note),
+            );
+        }
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        _ => ItemMenuResult::NoResponse,
+    }
+}
+
 pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let ammo = gs.ecs.read_storage::<Ammo>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -259,7 +615,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         .filter(|item| item.0.owner == *player_entity);
     let count = inventory.count();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
@@ -289,6 +645,7 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         .filter(|item| item.1.owner == *player_entity)
         .enumerate()
     {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -311,9 +668,11 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        match ammo.get(entity) {
+            Some(stack) => ctx.print(21, y, format!("{} ({})", name.name, stack.count)),
+            None => ctx.print(21, y, &name.name),
+        }
         equippable.push(entity);
-        y += 1;
     }
 
     match ctx.key {
@@ -345,11 +704,11 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
         .filter(|item| item.0.owner == *player_entity);
     let count = inventory.count();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let y = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
         y - 2,
-        31,
+        41,
         (count + 3) as i32,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
@@ -370,11 +729,12 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
     );
 
     let mut equippable: Vec<Entity> = Vec::new();
-    for (j, (entity, _pack, name)) in (&entities, &backpack, &names)
+    for (j, (entity, equipped, name)) in (&entities, &backpack, &names)
         .join()
         .filter(|item| item.1.owner == *player_entity)
         .enumerate()
     {
+        let y = y + j as i32;
         ctx.set(
             17,
             y,
@@ -397,9 +757,8 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(21, y, format!("{:<9} {}", equipped.slot.name(), name.name));
         equippable.push(entity);
-        y += 1;
     }
 
     match ctx.key {
@@ -445,8 +804,10 @@ pub fn ranged_target(
         for idx in visible.visible_tiles.iter() {
             let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE));
-                available_cells.push(idx);
+                if let Some(screen) = camera::map_to_screen(&gs.ecs, *idx) {
+                    ctx.set_bg(screen.x, screen.y, RGB::named(rltk::BLUE));
+                    available_cells.push(idx);
+                }
             }
         }
     } else {
@@ -455,19 +816,17 @@ pub fn ranged_target(
 
     // Draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
+    let mouse_map_pos = camera::screen_to_map(&gs.ecs, mouse_pos);
     let mut valid_target = false;
     for idx in available_cells.iter() {
-        if idx.x == mouse_pos.0 && idx.y == mouse_pos.1 {
+        if idx.x == mouse_map_pos.x && idx.y == mouse_map_pos.y {
             valid_target = true;
         }
     }
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, Some(mouse_map_pos));
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
@@ -611,6 +970,593 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     }
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum CreationStep {
+    Class { selection: usize },
+    StatMethod { selection: usize },
+    AssignStat { bonus: usize, selection: usize },
+    Skills { selection: usize },
+    Name,
+    Party { selection: usize },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum CharacterCreationResult {
+    NoResponse { step: CreationStep },
+    Cancel,
+    Finished,
+}
+
+/// The character creation screens: class, stats, skills and name, in that order, then either
+/// another character or setting out. The character is built up in the `CharacterDraft`
+/// resource as the player goes, and joins the `PartyDraft` once named.
+pub fn character_creation(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    step: CreationStep,
+) -> CharacterCreationResult {
+    let mut draft = gs.ecs.write_resource::<CharacterDraft>();
+
+    ctx.print_color_centered(
+        4,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Create Your Character",
+    );
+    draw_character_summary(&draft, ctx);
+    ctx.print_color(
+        4,
+        45,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "Up/Down to move, ENTER to choose, ESCAPE to return to the menu",
+    );
+
+    if ctx.key == Some(VirtualKeyCode::Escape) {
+        return CharacterCreationResult::Cancel;
+    }
+
+    match step {
+        CreationStep::Class { selection } => {
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "Choose a class:",
+            );
+            let options: Vec<String> = CharacterClass::ALL
+                .iter()
+                .map(|class| class.name().to_string())
+                .collect();
+            draw_choice_options(ctx, 10, &options, selection);
+            ctx.print(4, 16, CharacterClass::ALL[selection].description());
+            draft.class = CharacterClass::ALL[selection];
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => CharacterCreationResult::NoResponse {
+                    step: CreationStep::Class { selection },
+                },
+                (_, true) => CharacterCreationResult::NoResponse {
+                    step: CreationStep::StatMethod { selection: 0 },
+                },
+            }
+        }
+        CreationStep::StatMethod { selection } => {
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "How do you want your stats?",
+            );
+            let options = vec![
+                "Assign the standard array (+2, +1, +1, 0, 0, 0)".to_string(),
+                "Roll 3d6 for each stat".to_string(),
+            ];
+            draw_choice_options(ctx, 10, &options, selection);
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => CharacterCreationResult::NoResponse {
+                    step: CreationStep::StatMethod { selection },
+                },
+                (0, true) => {
+                    draft.stats = [0; 6];
+                    CharacterCreationResult::NoResponse {
+                        step: CreationStep::AssignStat {
+                            bonus: 0,
+                            selection: 0,
+                        },
+                    }
+                }
+                (_, true) => {
+                    let mut rng = gs.ecs.write_resource::<rltk::RandomNumberGenerator>();
+                    draft.roll_stats(&mut rng);
+                    CharacterCreationResult::NoResponse {
+                        step: CreationStep::Skills { selection: 0 },
+                    }
+                }
+            }
+        }
+        CreationStep::AssignStat { bonus, selection } => {
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                format!("Which stat gets +{}?", STAT_ARRAY[bonus]),
+            );
+            // Only stats that haven't had a bonus yet are on offer
+            let unassigned: Vec<usize> = (0..STAT_NAMES.len())
+                .filter(|i| draft.stats[*i] == 0)
+                .collect();
+            let options: Vec<String> = unassigned
+                .iter()
+                .map(|i| STAT_NAMES[*i].to_string())
+                .collect();
+            draw_choice_options(ctx, 10, &options, selection);
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => CharacterCreationResult::NoResponse {
+                    step: CreationStep::AssignStat { bonus, selection },
+                },
+                (selection, true) => {
+                    draft.stats[unassigned[selection]] = STAT_ARRAY[bonus];
+                    if bonus + 1 < STAT_ARRAY.len() {
+                        CharacterCreationResult::NoResponse {
+                            step: CreationStep::AssignStat {
+                                bonus: bonus + 1,
+                                selection: 0,
+                            },
+                        }
+                    } else {
+                        CharacterCreationResult::NoResponse {
+                            step: CreationStep::Skills { selection: 0 },
+                        }
+                    }
+                }
+            }
+        }
+        CreationStep::Skills { selection } => {
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                format!("Pick {} skills:", SKILL_CHOICES),
+            );
+            let options: Vec<String> = Skill::ALL
+                .iter()
+                .map(|skill| {
+                    let mark = if draft.skills.contains(skill) {
+                        "[x]"
+                    } else {
+                        "[ ]"
+                    };
+                    format!("{} {}", mark, skill.name())
+                })
+                .collect();
+            draw_choice_options(ctx, 10, &options, selection);
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => CharacterCreationResult::NoResponse {
+                    step: CreationStep::Skills { selection },
+                },
+                (selection, true) => {
+                    let skill = Skill::ALL[selection];
+                    if draft.skills.contains(&skill) {
+                        draft.skills.retain(|s| *s != skill);
+                    } else {
+                        draft.skills.push(skill);
+                    }
+                    if draft.skills.len() < SKILL_CHOICES {
+                        CharacterCreationResult::NoResponse {
+                            step: CreationStep::Skills { selection },
+                        }
+                    } else {
+                        CharacterCreationResult::NoResponse {
+                            step: CreationStep::Name,
+                        }
+                    }
+                }
+            }
+        }
+        CreationStep::Name => {
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "What is your name? (ENTER when done)",
+            );
+            ctx.print_color(
+                6,
+                10,
+                RGB::named(rltk::MAGENTA),
+                RGB::named(rltk::BLACK),
+                format!("{}_", draft.name),
+            );
+
+            match ctx.key {
+                Some(VirtualKeyCode::Return) => {
+                    if draft.name.trim().is_empty() {
+                        draft.name = "Adventurer".to_string();
+                    }
+                    let mut party = gs.ecs.write_resource::<PartyDraft>();
+                    party.members.push(draft.clone());
+                    CharacterCreationResult::NoResponse {
+                        step: CreationStep::Party { selection: 0 },
+                    }
+                }
+                Some(VirtualKeyCode::Back) => {
+                    draft.name.pop();
+                    CharacterCreationResult::NoResponse { step }
+                }
+                Some(VirtualKeyCode::Space) => {
+                    if !draft.name.is_empty() && draft.name.len() < MAX_NAME_LENGTH {
+                        draft.name.push(' ');
+                    }
+                    CharacterCreationResult::NoResponse { step }
+                }
+                Some(key) => {
+                    let letter = rltk::letter_to_option(key);
+                    if letter > -1 && draft.name.len() < MAX_NAME_LENGTH {
+                        let c = (b'a' + letter as u8) as char;
+                        if ctx.shift || draft.name.is_empty() || draft.name.ends_with(' ') {
+                            draft.name.push(c.to_ascii_uppercase());
+                        } else {
+                            draft.name.push(c);
+                        }
+                    }
+                    CharacterCreationResult::NoResponse { step }
+                }
+                None => CharacterCreationResult::NoResponse { step },
+            }
+        }
+        CreationStep::Party { selection } => {
+            let party = gs.ecs.fetch::<PartyDraft>();
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "Your party:",
+            );
+            for (i, member) in party.members.iter().enumerate() {
+                ctx.print(
+                    6,
+                    10 + i as i32,
+                    format!("{}, the {}", member.name, member.class.name()),
+                );
+            }
+            let mut options = vec!["Set out".to_string()];
+            if party.members.len() < MAX_PARTY_SIZE {
+                options.push("Add another adventurer".to_string());
+            }
+            draw_choice_options(ctx, 11 + party.members.len() as i32, &options, selection);
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => CharacterCreationResult::NoResponse {
+                    step: CreationStep::Party { selection },
+                },
+                (0, true) => CharacterCreationResult::Finished,
+                (_, true) => {
+                    *draft = CharacterDraft::new();
+                    CharacterCreationResult::NoResponse {
+                        step: CreationStep::Class { selection: 0 },
+                    }
+                }
+            }
+        }
+    }
+}
+
+const MAX_NAME_LENGTH: usize = 20;
+
+fn draw_character_summary(draft: &CharacterDraft, ctx: &mut Rltk) {
+    let x = 52;
+    ctx.draw_box(
+        x - 2,
+        7,
+        27,
+        20,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    let name = if draft.name.is_empty() {
+        "(unnamed)"
+    } else {
+        &draft.name
+    };
+    ctx.print_color(
+        x,
+        8,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        name,
+    );
+    ctx.print(x, 9, draft.class.name());
+    for (i, (stat_name, value)) in STAT_NAMES.iter().zip(draft.stats.iter()).enumerate() {
+        ctx.print(x, 11 + i as i32, format!("{} {:+}", stat_name, value));
+    }
+    ctx.print(x, 18, format!("HP  {}", draft.max_hp()));
+    ctx.print(x, 20, "Skills:");
+    for (i, skill) in draft.skills.iter().enumerate() {
+        ctx.print(x + 2, 21 + i as i32, skill.name());
+    }
+    ctx.print(x, 24, "Abilities:");
+    for (i, ability) in draft.class.starting_abilities().iter().enumerate() {
+        ctx.print(x + 2, 25 + i as i32, ability.name());
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LevelUpStep {
+    Stat {
+        selection: usize,
+    },
+    Ability {
+        stat: Option<usize>,
+        selection: usize,
+    },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LevelUpResult {
+    NoResponse {
+        step: LevelUpStep,
+    },
+    Finished {
+        stat: Option<usize>,
+        ability: Option<Ability>,
+    },
+}
+
+/// The level-up screens for `member` of the party: raise a stat, then pick up a new class
+/// ability. Either step is skipped when there is nothing left to choose from.
+pub fn level_up(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    member: Entity,
+    step: LevelUpStep,
+) -> LevelUpResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let experience = gs.ecs.read_storage::<Experience>();
+    let classes = gs.ecs.read_storage::<Class>();
+    let abilities = gs.ecs.read_storage::<Abilities>();
+    let names = gs.ecs.read_storage::<Name>();
+
+    let level = experience.get(member).map_or(1, |exp| exp.level) + 1;
+    let who = match names.get(member) {
+        Some(name) if member != *player_entity => format!("{} has", name.name),
+        _ => "You have".to_string(),
+    };
+    ctx.print_color_centered(
+        4,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        format!("{} reached level {}!", who, level),
+    );
+    ctx.print_color(
+        4,
+        45,
+        RGB::named(rltk::GRAY),
+        RGB::named(rltk::BLACK),
+        "Up/Down to move, ENTER to choose",
+    );
+
+    match step {
+        LevelUpStep::Stat { selection } => {
+            let values = stat_values(combat_stats.get(member).unwrap());
+            let raisable: Vec<usize> = (0..STAT_NAMES.len())
+                .filter(|i| values[*i] < MAX_STAT)
+                .collect();
+            if raisable.is_empty() {
+                return LevelUpResult::NoResponse {
+                    step: LevelUpStep::Ability {
+                        stat: None,
+                        selection: 0,
+                    },
+                };
+            }
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "Raise a stat by one:",
+            );
+            let options: Vec<String> = raisable
+                .iter()
+                .map(|i| format!("{} {:+}", STAT_NAMES[*i], values[*i]))
+                .collect();
+            draw_choice_options(ctx, 10, &options, selection);
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => LevelUpResult::NoResponse {
+                    step: LevelUpStep::Stat { selection },
+                },
+                (selection, true) => LevelUpResult::NoResponse {
+                    step: LevelUpStep::Ability {
+                        stat: Some(raisable[selection]),
+                        selection: 0,
+                    },
+                },
+            }
+        }
+        LevelUpStep::Ability { stat, selection } => {
+            let choices: Vec<Ability> = classes
+                .get(member)
+                .map_or(&[][..], |c| c.class.advanced_abilities())
+                .iter()
+                .filter(|a| !Abilities::has(&abilities, member, **a))
+                .copied()
+                .collect();
+            if choices.is_empty() {
+                return LevelUpResult::Finished {
+                    stat,
+                    ability: None,
+                };
+            }
+            ctx.print_color(
+                4,
+                8,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "Choose a new ability:",
+            );
+            let options: Vec<String> = choices
+                .iter()
+                .map(|ability| ability.name().to_string())
+                .collect();
+            draw_choice_options(ctx, 10, &options, selection);
+            ctx.print(4, 16, choices[selection].description());
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => LevelUpResult::NoResponse {
+                    step: LevelUpStep::Ability { stat, selection },
+                },
+                (selection, true) => LevelUpResult::Finished {
+                    stat,
+                    ability: Some(choices[selection]),
+                },
+            }
+        }
+    }
+}
+
+fn draw_choice_options(ctx: &mut Rltk, y: i32, options: &[String], selection: usize) {
+    for (i, option) in options.iter().enumerate() {
+        let fg = if i == selection {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color(6, y + i as i32, fg, RGB::named(rltk::BLACK), option);
+    }
+}
+
+/// Moves the highlight with the arrow keys; returns the new selection, and whether it was
+/// chosen with ENTER.
+fn choice_menu_input(ctx: &mut Rltk, selection: usize, count: usize) -> (usize, bool) {
+    match ctx.key {
+        Some(VirtualKeyCode::Up) => ((selection + count - 1) % count, false),
+        Some(VirtualKeyCode::Down) => ((selection + 1) % count, false),
+        Some(VirtualKeyCode::Return) => (selection, true),
+        _ => (selection, false),
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LastBreathStep {
+    Roll,
+    Rolled { total: i32, outcome: Outcome },
+    Bargain { selection: usize },
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LastBreathResult {
+    NoResponse { step: LastBreathStep },
+    Roll,
+    Stabilise,
+    Bargain(Bargain),
+    Died,
+}
+
+/// Death's door: roll 2d6, see how it went, and strike a bargain on a 7-9.
+pub fn last_breath(gs: &mut State, ctx: &mut Rltk, step: LastBreathStep) -> LastBreathResult {
+    ctx.print_color_centered(
+        4,
+        RGB::named(rltk::RED),
+        RGB::named(rltk::BLACK),
+        "Last Breath",
+    );
+    ctx.print_color_centered(
+        7,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        "You lie dying, and Death stands over you.",
+    );
+
+    match step {
+        LastBreathStep::Roll => {
+            ctx.print_color_centered(
+                10,
+                RGB::named(rltk::MAGENTA),
+                RGB::named(rltk::BLACK),
+                "Press ENTER to roll 2d6.",
+            );
+            match ctx.key {
+                Some(VirtualKeyCode::Return) => LastBreathResult::Roll,
+                _ => LastBreathResult::NoResponse { step },
+            }
+        }
+        LastBreathStep::Rolled { total, outcome } => {
+            ctx.print_color_centered(
+                10,
+                RGB::named(rltk::YELLOW),
+                RGB::named(rltk::BLACK),
+                format!("You rolled {}.", total),
+            );
+            let verdict = match outcome {
+                Outcome::Success => "Death passes you by, for now.",
+                Outcome::Partial => "Death offers you a bargain.",
+                Outcome::Miss => "Your time has come.",
+            };
+            ctx.print_color_centered(
+                12,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                verdict,
+            );
+            ctx.print_color_centered(
+                15,
+                RGB::named(rltk::MAGENTA),
+                RGB::named(rltk::BLACK),
+                "Press ENTER to continue.",
+            );
+            match (ctx.key, outcome) {
+                (Some(VirtualKeyCode::Return), Outcome::Success) => LastBreathResult::Stabilise,
+                (Some(VirtualKeyCode::Return), Outcome::Partial) => LastBreathResult::NoResponse {
+                    step: LastBreathStep::Bargain { selection: 0 },
+                },
+                (Some(VirtualKeyCode::Return), Outcome::Miss) => LastBreathResult::Died,
+                _ => LastBreathResult::NoResponse { step },
+            }
+        }
+        LastBreathStep::Bargain { selection } => {
+            ctx.print_color(
+                4,
+                10,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "What will you give up to live?",
+            );
+            let bargains = last_breath::bargains(&gs.ecs);
+            let options: Vec<String> = bargains
+                .iter()
+                .map(|bargain| last_breath::describe(&gs.ecs, *bargain))
+                .collect();
+            draw_choice_options(ctx, 12, &options, selection);
+            ctx.print_color(
+                4,
+                45,
+                RGB::named(rltk::GRAY),
+                RGB::named(rltk::BLACK),
+                "Up/Down to move, ENTER to choose",
+            );
+
+            match choice_menu_input(ctx, selection, options.len()) {
+                (selection, false) => LastBreathResult::NoResponse {
+                    step: LastBreathStep::Bargain { selection },
+                },
+                (selection, true) => LastBreathResult::Bargain(bargains[selection]),
+            }
+        }
+    }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult {
     NoSelection,
@@ -0,0 +1,123 @@
+use super::{
+    character::DEX, gamelog::GameLog, raws::RawMaster, roll::roll_2d6, Armor, CombatStats,
+    Debilities, Equipped, Faction, Initiative, Monster, Name, PartyMember, Position, Reaction,
+    Reputation, Viewshed,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Starts and ends encounters. Once anyone in the party and a hostile monster catch sight of each
+/// other, the whole party and every monster involved roll 2d6+DEX for initiative, and anyone who
+/// joins the fight later rolls as they do. When no hostile monster can see the party or be seen
+/// by them, the encounter is over and the turn order is forgotten.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, Initiative>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Armor>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, Debilities>,
+        ReadExpect<'a, RawMaster>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Reputation>,
+        ReadStorage<'a, PartyMember>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            mut log,
+            mut rng,
+            mut initiative,
+            monsters,
+            viewsheds,
+            positions,
+            combat_stats,
+            armor,
+            equipped,
+            debilities,
+            raws,
+            factions,
+            reputations,
+            members,
+            names,
+        ) = data;
+
+        let mut party: Vec<(Entity, Point, &Viewshed)> =
+            (&entities, &members, &positions, &viewsheds)
+                .join()
+                .map(|(entity, _, pos, viewshed)| (entity, Point::new(pos.x, pos.y), viewshed))
+                .collect();
+        party.sort_by_key(|(entity, ..)| members.get(*entity).map(|m| m.slot));
+        let in_sight: Vec<Entity> = (&entities, &monsters, &viewsheds, &positions, &combat_stats)
+            .join()
+            .filter(|(entity, _, viewshed, pos, stats)| {
+                let there = Point::new(pos.x, pos.y);
+                stats.hp > 0
+                    && Faction::reaction(&raws, &factions, &reputations, *entity, *player_entity)
+                        == Reaction::Attack
+                    && party.iter().any(|(_, here, view)| {
+                        viewshed.visible_tiles.contains(here) || view.visible_tiles.contains(&there)
+                    })
+            })
+            .map(|(entity, ..)| entity)
+            .collect();
+
+        let in_encounter = initiative.get(*player_entity).is_some();
+        if in_sight.is_empty() {
+            if in_encounter {
+                log.entries.push("The fight is over.".to_string());
+            }
+            initiative.clear();
+            return;
+        }
+
+        let mut roll_for = |entity: Entity| -> i32 {
+            let dex = combat_stats.get(entity).map_or(0, |stats| stats.dex)
+                + Armor::heavy_penalty(&armor, &equipped, entity)
+                + Debilities::penalty(&debilities, entity, DEX);
+            roll_2d6(&mut rng, dex).0
+        };
+        if !in_encounter {
+            let mut own = 0;
+            let mut others: Vec<String> = Vec::new();
+            for (member, ..) in party.iter() {
+                let roll = roll_for(*member);
+                initiative
+                    .insert(*member, Initiative { roll })
+                    .expect("Unable to insert initiative");
+                if *member == *player_entity {
+                    own = roll;
+                } else if let Some(name) = names.get(*member) {
+                    others.push(format!("{} {}", name.name, roll));
+                }
+            }
+            let mut message = format!("To arms! You roll {} for initiative", own);
+            if !others.is_empty() {
+                message += &format!(" ({})", others.join(", "));
+            }
+            message.push('.');
+            log.entries.push(message);
+        }
+        for entity in in_sight {
+            if initiative.get(entity).is_none() {
+                let roll = roll_for(entity);
+                initiative
+                    .insert(entity, Initiative { roll })
+                    .expect("Unable to insert initiative");
+            }
+        }
+    }
+}
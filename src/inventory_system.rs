@@ -1,7 +1,11 @@
 use super::{
-    gamelog::GameLog, AreaOfEffect, CombatStats, Confusion, Consumable, Equippable, Equipped,
-    InBackpack, InflictsDamage, Map, Name, Position, ProvidesHealing, SufferDamage,
-    WantsToDropItem, WantsToPickupItem, WantsToRemoveItem, WantsToUseItem,
+    character::{Ability, HEALER_BONUS, SPELLCRAFT_BONUS},
+    gamelog::GameLog,
+    Abilities, Ammo, AreaOfEffect, CombatStats, Consumable, CuresDebilities, Debilities, Energy,
+    EquipmentSlot, Equippable, Equipped, InBackpack, InflictsDamage, InflictsStatus, Map, Name,
+    PartyMember, Position, ProvidesHealing, StatusEffects, SufferDamage, WantsToDropItem,
+    WantsToPickupItem, WantsToRemoveItem, WantsToUseItem, Weapon, WeaponTag, ACTION_COST,
+    QUICK_ACTION_COST,
 };
 use specs::prelude::*;
 
@@ -10,28 +14,56 @@ pub struct ItemCollectionSystem {}
 impl<'a> System<'a> for ItemCollectionSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
+        Entities<'a>,
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Ammo>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            entities,
+            player_entity,
+            mut gamelog,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            mut ammo,
+        ) = data;
 
         for pickup in wants_pickup.join() {
-            positions.remove(pickup.item);
-            backpack
-                .insert(
-                    pickup.item,
-                    InBackpack {
-                        owner: pickup.collected_by,
-                    },
+            // Ammo goes onto a stack of the same kind, if there's one in the pack already
+            let stack = ammo.get(pickup.item).and_then(|picked_up| {
+                Ammo::carried_by(
+                    &entities,
+                    &ammo,
+                    &backpack,
+                    pickup.collected_by,
+                    picked_up.kind,
                 )
-                .expect("Unable to insert backpack entry");
+                .map(|stack| (stack, picked_up.count))
+            });
+            if let Some((stack, count)) = stack {
+                if let Some(stack) = ammo.get_mut(stack) {
+                    stack.count += count;
+                }
+                entities.delete(pickup.item).expect("Unable to delete");
+            } else {
+                positions.remove(pickup.item);
+                backpack
+                    .insert(
+                        pickup.item,
+                        InBackpack {
+                            owner: pickup.collected_by,
+                        },
+                    )
+                    .expect("Unable to insert backpack entry");
+            }
 
             if pickup.collected_by == *player_entity {
                 gamelog.entries.push(format!(
@@ -45,6 +77,9 @@ impl<'a> System<'a> for ItemCollectionSystem {
     }
 }
 
+/// Uses items: equipping, healing, curing, damaging and passing on statuses, on the user or
+/// whatever is at the target. Monsters cast their spells through here too. The player pays for
+/// using something; a monster's go is paid for by `MonsterAI`.
 pub struct ItemUseSystem {}
 
 impl<'a> System<'a> for ItemUseSystem {
@@ -62,10 +97,17 @@ impl<'a> System<'a> for ItemUseSystem {
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, AreaOfEffect>,
-        WriteStorage<'a, Confusion>,
+        ReadStorage<'a, InflictsStatus>,
+        WriteStorage<'a, StatusEffects>,
         ReadStorage<'a, Equippable>,
         WriteStorage<'a, Equipped>,
         WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Abilities>,
+        ReadStorage<'a, Weapon>,
+        ReadStorage<'a, CuresDebilities>,
+        WriteStorage<'a, Debilities>,
+        WriteStorage<'a, Energy>,
+        ReadStorage<'a, PartyMember>,
     );
 
     #[allow(clippy::cognitive_complexity)]
@@ -83,20 +125,38 @@ impl<'a> System<'a> for ItemUseSystem {
             mut combat_stats,
             mut suffer_damage,
             aoe,
-            mut confused,
+            inflicts_status,
+            mut status_effects,
             equippable,
             mut equipped,
             mut backpack,
+            abilities,
+            weapons,
+            cures_debilities,
+            mut debilities,
+            mut energy,
+            members,
         ) = data;
 
         for (entity, useitem) in (&entities, &wants_use).join() {
             let mut used_item = true;
 
+            if entity == *player_entity {
+                // Something used up on the spot, like a potion, is quicker than aiming a scroll
+                let quick = useitem.target.is_none() && consumables.get(useitem.item).is_some();
+                let cost = if quick {
+                    QUICK_ACTION_COST
+                } else {
+                    ACTION_COST
+                };
+                Energy::spend(&mut energy, entity, cost);
+            }
+
             // Targeting
             let mut targets: Vec<Entity> = Vec::new();
             match useitem.target {
                 None => {
-                    targets.push(*player_entity);
+                    targets.push(entity);
                 }
                 Some(target) => {
                     let area_effect = aoe.get(useitem.item);
@@ -134,12 +194,33 @@ impl<'a> System<'a> for ItemUseSystem {
                     let target_slot = can_equip.slot;
                     let target = targets[0];
 
-                    // Remove any items the target has in the item's slot
+                    // A two-handed weapon needs the off hand free, and the off hand can't be
+                    // used while holding one
+                    let two_handed = |item: Entity| {
+                        weapons
+                            .get(item)
+                            .is_some_and(|weapon| weapon.has(WeaponTag::TwoHanded))
+                    };
+                    let mut slots_needed = vec![target_slot];
+                    if two_handed(useitem.item) {
+                        slots_needed.push(EquipmentSlot::OffHand);
+                    }
+                    if target_slot == EquipmentSlot::OffHand {
+                        let holding_two_handed = (&entities, &equipped).join().any(|(item, e)| {
+                            e.owner == target && e.slot == EquipmentSlot::Melee && two_handed(item)
+                        });
+                        if holding_two_handed {
+                            slots_needed.push(EquipmentSlot::Melee);
+                        }
+                    }
+
+                    // Remove any items the target has in those slots
                     let mut to_unequip: Vec<Entity> = Vec::new();
                     for (item_entity, already_equipped, name) in
                         (&entities, &equipped, &names).join()
                     {
-                        if already_equipped.owner == target && already_equipped.slot == target_slot
+                        if already_equipped.owner == target
+                            && slots_needed.contains(&already_equipped.slot)
                         {
                             to_unequip.push(item_entity);
                             if target == *player_entity {
@@ -180,37 +261,88 @@ impl<'a> System<'a> for ItemUseSystem {
                 None => {}
                 Some(healer) => {
                     used_item = false;
+                    let mut heal_amount = healer.heal_amount;
+                    if Abilities::has(&abilities, entity, Ability::Healer) {
+                        heal_amount += HEALER_BONUS;
+                    }
                     for target in targets.iter() {
                         let stats = combat_stats.get_mut(*target);
                         if let Some(stats) = stats {
-                            stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
+                            stats.hp = i32::min(stats.max_hp, stats.hp + heal_amount);
                             if entity == *player_entity {
                                 gamelog.entries.push(format!(
                                     "You use the {}, healing {} hp.",
                                     names.get(useitem.item).unwrap().name,
-                                    healer.heal_amount
+                                    heal_amount
                                 ));
                             }
+                            // A healer's care mends more than hit points
+                            let mended = if Abilities::has(&abilities, entity, Ability::Healer) {
+                                debilities
+                                    .get_mut(*target)
+                                    .filter(|d| !d.debilities.is_empty())
+                                    .map(|d| d.debilities.remove(0))
+                            } else {
+                                None
+                            };
+                            if let (Some(debility), true) = (mended, *target == *player_entity) {
+                                gamelog
+                                    .entries
+                                    .push(format!("You are no longer {}.", debility.name()));
+                            }
                             used_item = true;
                         }
                     }
                 }
             }
 
+            // If it cures debilities, clear them all
+            if cures_debilities.get(useitem.item).is_some() {
+                used_item = false;
+                for target in targets.iter() {
+                    if let Some(target_debilities) = debilities.get_mut(*target) {
+                        target_debilities.debilities.clear();
+                    }
+                    if *target == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You use the {}, and feel whole again.",
+                            names.get(useitem.item).unwrap().name
+                        ));
+                    }
+                    used_item = true;
+                }
+            }
+
             // If it inflicts damage, apply it to the target cell
             let item_damages = inflict_damage.get(useitem.item);
             match item_damages {
                 None => {}
                 Some(damage) => {
                     used_item = false;
+                    let mut amount = damage.damage;
+                    if Abilities::has(&abilities, entity, Ability::Spellcraft) {
+                        amount += SPELLCRAFT_BONUS;
+                    }
                     for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
+                        SufferDamage::new_damage(
+                            &mut suffer_damage,
+                            *mob,
+                            amount,
+                            members.get(entity).map(|member| member.slot),
+                        );
                         if entity == *player_entity {
                             let mob_name = names.get(*mob).unwrap();
                             let item_name = names.get(useitem.item).unwrap();
                             gamelog.entries.push(format!(
                                 "You use {} on {}, inflicting {} hp.",
-                                item_name.name, mob_name.name, damage.damage
+                                item_name.name, mob_name.name, amount
+                            ));
+                        } else if let (Some(caster), Some(item_name), Some(mob_name)) =
+                            (names.get(entity), names.get(useitem.item), names.get(*mob))
+                        {
+                            gamelog.entries.push(format!(
+                                "{} casts {} on {}, inflicting {} hp.",
+                                caster.name, item_name.name, mob_name.name, amount
                             ));
                         }
 
@@ -219,33 +351,54 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
-            // Can it pass along confusion? Note the use of scopes to escape from the borrow checker!
-            let mut add_confusion = Vec::new();
-            {
-                let causes_confusion = confused.get(useitem.item);
-                match causes_confusion {
-                    None => {}
-                    Some(confusion) => {
-                        used_item = false;
-                        for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns));
-                            if entity == *player_entity {
+            // Can it pass along a status?
+            let item_status = inflicts_status.get(useitem.item);
+            match item_status {
+                None => {}
+                Some(inflicts) => {
+                    used_item = false;
+                    for mob in targets.iter() {
+                        if combat_stats.get(*mob).is_none() {
+                            continue;
+                        }
+                        StatusEffects::add(
+                            &mut status_effects,
+                            *mob,
+                            inflicts.status,
+                            inflicts.turns,
+                        );
+                        if entity == *player_entity {
+                            let item_name = names.get(useitem.item).unwrap();
+                            if *mob == *player_entity {
+                                gamelog.entries.push(format!(
+                                    "You use the {}. You are {}.",
+                                    item_name.name,
+                                    inflicts.status.name().to_lowercase()
+                                ));
+                            } else {
                                 let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(useitem.item).unwrap();
                                 gamelog.entries.push(format!(
-                                    "You use {} on {}, confusing them.",
-                                    item_name.name, mob_name.name
+                                    "You use {} on {}. They are {}.",
+                                    item_name.name,
+                                    mob_name.name,
+                                    inflicts.status.name().to_lowercase()
                                 ));
                             }
+                        } else if let (Some(caster), Some(item_name), Some(mob_name)) =
+                            (names.get(entity), names.get(useitem.item), names.get(*mob))
+                        {
+                            gamelog.entries.push(format!(
+                                "{} casts {} on {}. They are {}.",
+                                caster.name,
+                                item_name.name,
+                                mob_name.name,
+                                inflicts.status.name().to_lowercase()
+                            ));
                         }
+                        used_item = true;
                     }
                 }
             }
-            for mob in add_confusion.iter() {
-                confused
-                    .insert(mob.0, Confusion { turns: mob.1 })
-                    .expect("Unable to insert status");
-            }
 
             // If its a consumable, we delete it on use
             if used_item {
@@ -342,3 +495,158 @@ impl<'a> System<'a> for ItemRemoveSystem {
         wants_remove.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::AmmoKind;
+    use super::*;
+
+    struct Armory {
+        world: World,
+        greatsword: Entity,
+        sword: Entity,
+        shield: Entity,
+    }
+
+    fn armory() -> Armory {
+        let mut world = World::new();
+        System::setup(&mut ItemUseSystem {}, &mut world);
+        world.insert(GameLog { entries: vec![] });
+        world.insert(Map::new(1, 80, 43));
+        let player = world.create_entity().build();
+        world.insert(player);
+        let mut item = |name: &str, slot: EquipmentSlot, tags: Vec<WeaponTag>| {
+            let mut builder = world
+                .create_entity()
+                .with(Name {
+                    name: name.to_string(),
+                })
+                .with(Equippable { slot })
+                .with(InBackpack { owner: player });
+            if slot == EquipmentSlot::Melee {
+                builder = builder.with(Weapon {
+                    damage: rltk::DiceType::new(1, 8, 0),
+                    tags,
+                    ammo: None,
+                });
+            }
+            builder.build()
+        };
+        let greatsword = item(
+            "Greatsword",
+            EquipmentSlot::Melee,
+            vec![WeaponTag::TwoHanded],
+        );
+        let sword = item("Sword", EquipmentSlot::Melee, vec![]);
+        let shield = item("Shield", EquipmentSlot::OffHand, vec![]);
+        Armory {
+            world,
+            greatsword,
+            sword,
+            shield,
+        }
+    }
+
+    fn equip(world: &mut World, item: Entity) {
+        let player = *world.fetch::<Entity>();
+        world
+            .write_storage::<WantsToUseItem>()
+            .insert(player, WantsToUseItem { item, target: None })
+            .expect("Unable to insert intent");
+        ItemUseSystem {}.run_now(world);
+        world.maintain();
+    }
+
+    fn equipped(world: &World, item: Entity) -> bool {
+        world.read_storage::<Equipped>().contains(item)
+    }
+
+    #[test]
+    fn a_two_handed_weapon_puts_the_shield_away() {
+        let Armory {
+            mut world,
+            greatsword,
+            shield,
+            ..
+        } = armory();
+        equip(&mut world, shield);
+        equip(&mut world, greatsword);
+        assert!(equipped(&world, greatsword));
+        assert!(!equipped(&world, shield));
+        assert!(world.read_storage::<InBackpack>().contains(shield));
+    }
+
+    #[test]
+    fn a_shield_puts_the_two_handed_weapon_away() {
+        let Armory {
+            mut world,
+            greatsword,
+            shield,
+            ..
+        } = armory();
+        equip(&mut world, greatsword);
+        equip(&mut world, shield);
+        assert!(equipped(&world, shield));
+        assert!(!equipped(&world, greatsword));
+    }
+
+    #[test]
+    fn a_one_handed_weapon_keeps_the_shield() {
+        let Armory {
+            mut world,
+            greatsword,
+            sword,
+            shield,
+        } = armory();
+        equip(&mut world, shield);
+        equip(&mut world, sword);
+        assert!(equipped(&world, shield));
+        assert!(equipped(&world, sword));
+        equip(&mut world, greatsword);
+        assert!(!equipped(&world, sword));
+        assert!(!equipped(&world, shield));
+    }
+
+    fn pick_up(world: &mut World, item: Entity) {
+        let collected_by = *world.fetch::<Entity>();
+        world
+            .write_storage::<WantsToPickupItem>()
+            .insert(collected_by, WantsToPickupItem { collected_by, item })
+            .expect("Unable to insert intent");
+        ItemCollectionSystem {}.run_now(world);
+        world.maintain();
+    }
+
+    #[test]
+    fn ammo_stacks_with_its_own_kind() {
+        let mut world = World::new();
+        System::setup(&mut ItemCollectionSystem {}, &mut world);
+        world.insert(GameLog { entries: vec![] });
+        let player = world.create_entity().build();
+        world.insert(player);
+        let mut ammo = |kind: AmmoKind, count: i32| {
+            world
+                .create_entity()
+                .with(Name {
+                    name: format!("{:?}", kind),
+                })
+                .with(Position { x: 1, y: 1 })
+                .with(Ammo { kind, count })
+                .build()
+        };
+        let arrows = ammo(AmmoKind::Arrow, 5);
+        let more_arrows = ammo(AmmoKind::Arrow, 3);
+        let stones = ammo(AmmoKind::Stone, 4);
+
+        pick_up(&mut world, arrows);
+        pick_up(&mut world, more_arrows);
+        pick_up(&mut world, stones);
+
+        assert!(!world.is_alive(more_arrows));
+        let ammo = world.read_storage::<Ammo>();
+        assert_eq!(ammo.get(arrows).unwrap().count, 8);
+        assert_eq!(ammo.get(stones).unwrap().count, 4);
+        let backpack = world.read_storage::<InBackpack>();
+        assert!(backpack.contains(arrows) && backpack.contains(stones));
+    }
+}
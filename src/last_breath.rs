@@ -0,0 +1,238 @@
+use super::{
+    character::{stat_mut, stat_values, CON, STAT_NAMES},
+    gamelog::GameLog,
+    roll::{roll_2d6, Outcome},
+    CombatStats, DeathsMark, Equipped, Name,
+};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+/// No bargain can take a stat below this.
+pub const MIN_STAT: i32 = -3;
+
+/// What Death will take to let the player live, on a 7-9.
+#[derive(PartialEq, Copy, Clone)]
+pub enum Bargain {
+    /// A point off one stat, by its place in `STAT_NAMES`, for good.
+    Stat(usize),
+    /// Something the player has equipped, gone for good.
+    Item(Entity),
+    /// A Death's mark, which makes every later Last Breath harder.
+    Curse,
+}
+
+/// The player is at death's door: roll 2d6, less one for every Death's mark they carry.
+pub fn roll(ecs: &mut World) -> (i32, Outcome) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let marks = ecs
+        .read_storage::<DeathsMark>()
+        .get(player_entity)
+        .map_or(0, |mark| mark.marks);
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let (total, outcome) = roll_2d6(&mut rng, -marks);
+
+    let mut log = ecs.write_resource::<GameLog>();
+    log.entries
+        .push(format!("You take your Last Breath, and roll {}.", total));
+    match outcome {
+        Outcome::Success => log
+            .entries
+            .push("Death passes you by, for now.".to_string()),
+        Outcome::Partial => log.entries.push("Death offers you a bargain.".to_string()),
+        Outcome::Miss => log.entries.push("Your time has come.".to_string()),
+    }
+    (total, outcome)
+}
+
+/// Brings the player back from the brink with a single hit point.
+pub fn stabilise(ecs: &mut World) {
+    let player_entity = *ecs.fetch::<Entity>();
+    if let Some(stats) = ecs.write_storage::<CombatStats>().get_mut(player_entity) {
+        stats.hp = 1;
+    }
+    ecs.write_resource::<GameLog>()
+        .entries
+        .push("You cling to life, with 1 hp.".to_string());
+}
+
+/// Everything Death would take: any stat with room to fall, anything equipped, or a mark.
+pub fn bargains(ecs: &World) -> Vec<Bargain> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut bargains: Vec<Bargain> = Vec::new();
+    if let Some(stats) = ecs.read_storage::<CombatStats>().get(player_entity) {
+        for (i, value) in stat_values(stats).iter().enumerate() {
+            if *value > MIN_STAT {
+                bargains.push(Bargain::Stat(i));
+            }
+        }
+    }
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    for (item, equipped_by) in (&entities, &equipped).join() {
+        if equipped_by.owner == player_entity {
+            bargains.push(Bargain::Item(item));
+        }
+    }
+    bargains.push(Bargain::Curse);
+    bargains
+}
+
+/// How a bargain reads in the Last Breath menu.
+pub fn describe(ecs: &World, bargain: Bargain) -> String {
+    match bargain {
+        Bargain::Stat(stat) => format!("Give up a point of {}, forever", STAT_NAMES[stat]),
+        Bargain::Item(item) => format!(
+            "Give up your {}",
+            ecs.read_storage::<Name>()
+                .get(item)
+                .map_or("gear", |n| n.name.as_str())
+        ),
+        Bargain::Curse => "Accept Death's mark: -1 to every Last Breath to come".to_string(),
+    }
+}
+
+/// Pays Death's price, then lets the player live on at 1 hp.
+pub fn accept(ecs: &mut World, bargain: Bargain) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let message = match bargain {
+        Bargain::Stat(stat) => {
+            let mut combat_stats = ecs.write_storage::<CombatStats>();
+            if let Some(stats) = combat_stats.get_mut(player_entity) {
+                *stat_mut(stats, stat) -= 1;
+                // Constitution is worth 3 hit points a point
+                if stat == CON {
+                    stats.max_hp = i32::max(1, stats.max_hp - 3);
+                }
+            }
+            format!("Death takes a point of your {}.", STAT_NAMES[stat])
+        }
+        Bargain::Item(item) => {
+            let name = ecs
+                .read_storage::<Name>()
+                .get(item)
+                .map_or("gear".to_string(), |n| n.name.clone());
+            ecs.delete_entity(item).expect("Unable to delete");
+            format!("Death takes your {}.", name)
+        }
+        Bargain::Curse => {
+            let mut marks = ecs.write_storage::<DeathsMark>();
+            if let Some(mark) = marks.get_mut(player_entity) {
+                mark.marks += 1;
+            } else {
+                marks
+                    .insert(player_entity, DeathsMark { marks: 1 })
+                    .expect("Unable to insert mark");
+            }
+            "Death marks you as its own.".to_string()
+        }
+    };
+    ecs.write_resource::<GameLog>().entries.push(message);
+    stabilise(ecs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{character::STR, EquipmentSlot};
+    use super::*;
+
+    fn at_deaths_door(seed: u64, con: i32) -> World {
+        let mut world = World::new();
+        world.register::<CombatStats>();
+        world.register::<DeathsMark>();
+        world.register::<Equipped>();
+        world.register::<Name>();
+        world.insert(GameLog { entries: vec![] });
+        world.insert(RandomNumberGenerator::seeded(seed));
+        let player = world
+            .create_entity()
+            .with(CombatStats {
+                str: 0,
+                dex: 0,
+                con,
+                int: 0,
+                wis: 0,
+                cha: 0,
+                max_hp: 10,
+                hp: 0,
+            })
+            .build();
+        world.insert(player);
+        world
+    }
+
+    fn player_stats(world: &World) -> CombatStats {
+        let player = *world.fetch::<Entity>();
+        world
+            .read_storage::<CombatStats>()
+            .get(player)
+            .unwrap()
+            .clone()
+    }
+
+    fn wield(world: &mut World, name: &str) -> Entity {
+        let owner = *world.fetch::<Entity>();
+        world
+            .create_entity()
+            .with(Name {
+                name: name.to_string(),
+            })
+            .with(Equipped {
+                owner,
+                slot: EquipmentSlot::Melee,
+            })
+            .build()
+    }
+
+    #[test]
+    fn death_bargains_for_stats_with_room_to_fall_gear_and_a_mark() {
+        let mut world = at_deaths_door(1, MIN_STAT);
+        let sword = wield(&mut world, "Sword");
+        let bargains = bargains(&world);
+        assert!(!bargains.contains(&Bargain::Stat(CON)));
+        assert_eq!(
+            bargains
+                .iter()
+                .filter(|b| matches!(b, Bargain::Stat(_)))
+                .count(),
+            5
+        );
+        assert!(bargains.contains(&Bargain::Item(sword)));
+        assert!(bargains.contains(&Bargain::Curse));
+    }
+
+    #[test]
+    fn every_bargain_leaves_the_player_on_1_hp() {
+        let mut world = at_deaths_door(1, 0);
+        accept(&mut world, Bargain::Stat(STR));
+        let stats = player_stats(&world);
+        assert_eq!((stats.str, stats.hp, stats.max_hp), (-1, 1, 10));
+
+        // CON takes 3 max hp down with it
+        let mut world = at_deaths_door(1, 1);
+        accept(&mut world, Bargain::Stat(CON));
+        let stats = player_stats(&world);
+        assert_eq!((stats.con, stats.hp, stats.max_hp), (0, 1, 7));
+
+        let mut world = at_deaths_door(1, 0);
+        let sword = wield(&mut world, "Sword");
+        accept(&mut world, Bargain::Item(sword));
+        world.maintain();
+        assert!(!world.is_alive(sword));
+        assert_eq!(player_stats(&world).hp, 1);
+    }
+
+    #[test]
+    fn deaths_marks_weigh_on_later_rolls() {
+        for seed in 0..20 {
+            let mut world = at_deaths_door(seed, 0);
+            let (unmarked, _) = roll(&mut world);
+
+            let mut world = at_deaths_door(seed, 0);
+            accept(&mut world, Bargain::Curse);
+            accept(&mut world, Bargain::Curse);
+            let (marked, outcome) = roll(&mut world);
+            assert_eq!(marked, unmarked - 2);
+            assert_eq!(outcome, Outcome::from_total(marked));
+        }
+    }
+}
@@ -3,10 +3,18 @@ use rltk::{GameState, Point, Rltk};
 use specs::prelude::*;
 use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
 
+mod camera;
+mod character;
+use character::{CharacterDraft, PartyDraft};
 mod components;
 pub use components::*;
 mod map;
 pub use map::*;
+mod map_builders;
+mod noise_system;
+use noise_system::NoiseSystem;
+mod party_system;
+use party_system::PartySystem;
 mod player;
 use player::*;
 mod rect;
@@ -19,15 +27,34 @@ mod map_indexing_system;
 use map_indexing_system::MapIndexingSystem;
 mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
+mod ranged_combat_system;
+use ranged_combat_system::RangedCombatSystem;
 mod damage_system;
+mod dijkstra_maps;
+use dijkstra_maps::{DijkstraMapSystem, DijkstraMaps};
+mod dungeon;
 use damage_system::DamageSystem;
+use dungeon::MasterDungeonMap;
+mod energy_system;
+use energy_system::EnergySystem;
 mod gamelog;
 mod gui;
+mod initiative_system;
+use initiative_system::InitiativeSystem;
 mod inventory_system;
+mod last_breath;
 mod spawner;
 use inventory_system::{ItemCollectionSystem, ItemDropSystem, ItemRemoveSystem, ItemUseSystem};
 pub mod random_table;
+pub mod raws;
+mod roll;
 pub mod saveload_system;
+mod seed;
+use seed::RunSeed;
+mod status_effect_system;
+use status_effect_system::StatusEffectSystem;
+mod trap_system;
+use trap_system::{TrapDetectionSystem, TrapSystem};
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState {
@@ -41,12 +68,27 @@ pub enum RunState {
         range: i32,
         item: Entity,
     },
+    ShowFireTargeting {
+        range: i32,
+    },
     MainMenu {
         menu_selection: gui::MainMenuSelection,
     },
+    CharacterCreation {
+        step: gui::CreationStep,
+    },
+    LevelUp {
+        member: Entity,
+        step: gui::LevelUpStep,
+    },
+    LastBreath {
+        step: gui::LastBreathStep,
+    },
     SaveGame,
     NextLevel,
+    PreviousLevel,
     ShowRemoveItem,
+    ShowCharacter,
     GameOver,
 }
 
@@ -56,14 +98,27 @@ pub struct State {
 
 impl State {
     fn run_systems(&mut self) {
+        let mut mapindex = MapIndexingSystem {};
+        mapindex.run_now(&self.ecs);
+        let mut traps = TrapSystem {};
+        traps.run_now(&self.ecs);
         let mut vis = VisibilitySystem {};
         vis.run_now(&self.ecs);
+        let mut dijkstra = DijkstraMapSystem {};
+        dijkstra.run_now(&self.ecs);
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
+        let mut detection = TrapDetectionSystem {};
+        detection.run_now(&self.ecs);
         let mut mob = MonsterAI {};
         mob.run_now(&self.ecs);
-        let mut mapindex = MapIndexingSystem {};
+        let mut party = PartySystem {};
+        party.run_now(&self.ecs);
         mapindex.run_now(&self.ecs);
         let mut melee = MeleeCombatSystem {};
         melee.run_now(&self.ecs);
+        let mut ranged = RangedCombatSystem {};
+        ranged.run_now(&self.ecs);
         let mut damage = DamageSystem {};
         damage.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem {};
@@ -74,6 +129,8 @@ impl State {
         drop_items.run_now(&self.ecs);
         let mut item_remove = ItemRemoveSystem {};
         item_remove.run_now(&self.ecs);
+        let mut noise = NoiseSystem {};
+        noise.run_now(&self.ecs);
 
         self.ecs.maintain();
     }
@@ -91,26 +148,13 @@ impl GameState for State {
 
         match newrunstate {
             RunState::MainMenu { .. } => {}
-            RunState::GameOver { .. } => {}
+            RunState::CharacterCreation { .. } => {}
+            RunState::LevelUp { .. } => {}
+            RunState::LastBreath { .. } => {}
+            RunState::GameOver => {}
             _ => {
-                draw_map(&self.ecs, ctx);
-
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-                    for (pos, render) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] {
-                            ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph)
-                        }
-                    }
-
-                    gui::draw_ui(&self.ecs, ctx);
-                }
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
             }
         }
 
@@ -124,14 +168,49 @@ impl GameState for State {
                 newrunstate = player_input(self, ctx);
             }
             RunState::PlayerTurn => {
+                let energy_before = self.player_energy();
                 self.run_systems();
                 self.ecs.maintain();
-                newrunstate = RunState::MonsterTurn;
+                // Anything the systems didn't put a price on costs a normal action
+                if self.player_energy() == energy_before {
+                    let player_entity = *self.ecs.fetch::<Entity>();
+                    Energy::spend(
+                        &mut self.ecs.write_storage::<Energy>(),
+                        player_entity,
+                        ACTION_COST,
+                    );
+                }
+                newrunstate = if self.player_ready() {
+                    RunState::AwaitingInput
+                } else {
+                    RunState::MonsterTurn
+                };
             }
             RunState::MonsterTurn => {
+                let delaying = {
+                    let player_entity = self.ecs.fetch::<Entity>();
+                    self.ecs
+                        .read_storage::<WantsToDelay>()
+                        .get(*player_entity)
+                        .is_some()
+                };
                 self.run_systems();
                 self.ecs.maintain();
-                newrunstate = RunState::AwaitingInput;
+                let new_round = !delaying && !self.monsters_ready() && !self.party_ready();
+                if new_round {
+                    self.pass_turn();
+                }
+                party_system::pass_control(&mut self.ecs, new_round);
+                newrunstate = if !delaying && !self.player_ready() {
+                    RunState::MonsterTurn
+                } else if let Some(member) = self.ready_to_level_up() {
+                    RunState::LevelUp {
+                        member,
+                        step: gui::LevelUpStep::Stat { selection: 0 },
+                    }
+                } else {
+                    RunState::AwaitingInput
+                };
             }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
@@ -143,8 +222,17 @@ impl GameState for State {
                         let is_ranged = self.ecs.read_storage::<Ranged>();
                         let is_item_ranged = is_ranged.get(item_entity);
                         if let Some(is_item_ranged) = is_item_ranged {
+                            let abilities = self.ecs.read_storage::<Abilities>();
+                            let mut range = is_item_ranged.range;
+                            if Abilities::has(
+                                &abilities,
+                                *self.ecs.fetch::<Entity>(),
+                                character::Ability::ArcaneReach,
+                            ) {
+                                range += character::ARCANE_REACH_BONUS;
+                            }
                             newrunstate = RunState::ShowTargeting {
-                                range: is_item_ranged.range,
+                                range,
                                 item: item_entity,
                             };
                         } else {
@@ -199,6 +287,11 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::ShowCharacter => {
+                if gui::show_character(self, ctx) == gui::ItemMenuResult::Cancel {
+                    newrunstate = RunState::AwaitingInput;
+                }
+            }
             RunState::ShowTargeting { range, item } => {
                 let result = gui::ranged_target(self, ctx, range);
                 match result.0 {
@@ -219,6 +312,20 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::ShowFireTargeting { range } => {
+                let result = gui::ranged_target(self, ctx, range);
+                match result {
+                    (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                    (gui::ItemMenuResult::Selected, Some(target)) => {
+                        let mut intent = self.ecs.write_storage::<WantsToShoot>();
+                        intent
+                            .insert(*self.ecs.fetch::<Entity>(), WantsToShoot { target })
+                            .expect("Unable to insert intent");
+                        newrunstate = RunState::PlayerTurn;
+                    }
+                    _ => {}
+                }
+            }
             RunState::MainMenu { .. } => {
                 let result = gui::main_menu(self, ctx);
                 match result {
@@ -228,9 +335,19 @@ impl GameState for State {
                         }
                     }
                     gui::MainMenuResult::Selected { selected } => match selected {
-                        gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
+                        gui::MainMenuSelection::NewGame => {
+                            self.ecs.insert(CharacterDraft::new());
+                            self.ecs.insert(PartyDraft::default());
+                            newrunstate = RunState::CharacterCreation {
+                                step: gui::CreationStep::Class { selection: 0 },
+                            };
+                        }
                         gui::MainMenuSelection::LoadGame => {
                             saveload_system::load_game(&mut self.ecs);
+                            // What stands where, and which doors are shut, isn't saved with the
+                            // map, so index it again before the first move
+                            let mut mapindex = MapIndexingSystem {};
+                            mapindex.run_now(&self.ecs);
                             newrunstate = RunState::AwaitingInput;
                             saveload_system::delete_save();
                         }
@@ -240,6 +357,75 @@ impl GameState for State {
                     },
                 }
             }
+            RunState::CharacterCreation { step } => {
+                let result = gui::character_creation(self, ctx, step);
+                match result {
+                    gui::CharacterCreationResult::NoResponse { step } => {
+                        newrunstate = RunState::CharacterCreation { step }
+                    }
+                    gui::CharacterCreationResult::Cancel => {
+                        newrunstate = RunState::MainMenu {
+                            menu_selection: gui::MainMenuSelection::NewGame,
+                        }
+                    }
+                    gui::CharacterCreationResult::Finished => {
+                        let seed = *self.ecs.fetch::<RunSeed>();
+                        self.new_run(seed);
+                        newrunstate = RunState::PreRun;
+                    }
+                }
+            }
+            RunState::LevelUp { member, step } => {
+                let result = gui::level_up(self, ctx, member, step);
+                match result {
+                    gui::LevelUpResult::NoResponse { step } => {
+                        newrunstate = RunState::LevelUp { member, step }
+                    }
+                    gui::LevelUpResult::Finished { stat, ability } => {
+                        character::level_up(&mut self.ecs, member, stat, ability);
+                        // Enough experience can carry someone up more than one level, and
+                        // others in the party may be due one too
+                        newrunstate = if let Some(member) = self.ready_to_level_up() {
+                            RunState::LevelUp {
+                                member,
+                                step: gui::LevelUpStep::Stat { selection: 0 },
+                            }
+                        } else {
+                            RunState::AwaitingInput
+                        };
+                    }
+                }
+            }
+            RunState::LastBreath { step } => {
+                let result = gui::last_breath(self, ctx, step);
+                match result {
+                    gui::LastBreathResult::NoResponse { step } => {
+                        newrunstate = RunState::LastBreath { step }
+                    }
+                    gui::LastBreathResult::Roll => {
+                        let (total, outcome) = last_breath::roll(&mut self.ecs);
+                        newrunstate = RunState::LastBreath {
+                            step: gui::LastBreathStep::Rolled { total, outcome },
+                        };
+                    }
+                    gui::LastBreathResult::Stabilise => {
+                        last_breath::stabilise(&mut self.ecs);
+                        newrunstate = self.carry_on();
+                    }
+                    gui::LastBreathResult::Bargain(bargain) => {
+                        last_breath::accept(&mut self.ecs, bargain);
+                        newrunstate = self.carry_on();
+                    }
+                    gui::LastBreathResult::Died => {
+                        // The run only ends with the last of the party
+                        newrunstate = if party_system::fall(&mut self.ecs) {
+                            self.carry_on()
+                        } else {
+                            RunState::GameOver
+                        };
+                    }
+                }
+            }
             RunState::GameOver => {
                 let result = gui::game_over(ctx);
                 match result {
@@ -262,6 +448,10 @@ impl GameState for State {
                 self.goto_next_level();
                 newrunstate = RunState::PreRun;
             }
+            RunState::PreviousLevel => {
+                self.goto_previous_level();
+                newrunstate = RunState::PreRun;
+            }
         }
 
         {
@@ -273,99 +463,116 @@ impl GameState for State {
 }
 
 impl State {
-    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
-        let entities = self.ecs.entities();
-        let player = self.ecs.read_storage::<Player>();
-        let backpack = self.ecs.read_storage::<InBackpack>();
-        let player_entity = self.ecs.fetch::<Entity>();
-        let equipped = self.ecs.read_storage::<Equipped>();
+    /// Whoever in the party has the experience for a level, starting with whoever is under
+    /// control and then in marching order. Only the living level up.
+    fn ready_to_level_up(&self) -> Option<Entity> {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        let experience = self.ecs.read_storage::<Experience>();
+        let combat_stats = self.ecs.read_storage::<CombatStats>();
+        let members = self.ecs.read_storage::<PartyMember>();
+        let mut party: Vec<(Entity, usize)> = (&self.ecs.entities(), &members)
+            .join()
+            .map(|(entity, member)| (entity, member.slot))
+            .collect();
+        party.sort_by_key(|(_, slot)| *slot);
+        std::iter::once(player_entity)
+            .chain(party.into_iter().map(|(entity, _)| entity))
+            .find(|entity| {
+                experience
+                    .get(*entity)
+                    .is_some_and(|exp| exp.can_level_up())
+                    && combat_stats.get(*entity).is_some_and(|stats| stats.hp > 0)
+            })
+    }
 
-        let mut to_delete: Vec<Entity> = Vec::new();
-        for entity in entities.join() {
-            let mut should_delete = true;
+    fn player_energy(&self) -> Option<i32> {
+        let player_entity = self.ecs.fetch::<Entity>();
+        let energy = self.ecs.read_storage::<Energy>();
+        energy.get(*player_entity).map(|e| e.energy)
+    }
 
-            // Don't delete the player
-            let p = player.get(entity);
-            if let Some(_p) = p {
-                should_delete = false;
-            }
+    /// Whether the player has the energy to act again before the monsters do.
+    fn player_ready(&self) -> bool {
+        let player_entity = self.ecs.fetch::<Entity>();
+        Energy::ready(&self.ecs.read_storage::<Energy>(), *player_entity)
+    }
 
-            // Don't delete the player's equipment
-            let bp = backpack.get(entity);
-            if let Some(bp) = bp {
-                if bp.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
+    /// Whether any monster still has the energy to act this turn.
+    fn monsters_ready(&self) -> bool {
+        let monsters = self.ecs.read_storage::<Monster>();
+        let energy = self.ecs.read_storage::<Energy>();
+        (&monsters, &energy).join().any(|(_, e)| e.energy >= 0)
+    }
 
-            let eq = equipped.get(entity);
-            if let Some(eq) = eq {
-                if eq.owner == *player_entity {
-                    should_delete = false;
-                }
-            }
+    /// Whether anyone in the party still has a go to come in this round of an encounter.
+    fn party_ready(&self) -> bool {
+        let members = self.ecs.read_storage::<PartyMember>();
+        let initiative = self.ecs.read_storage::<Initiative>();
+        let energy = self.ecs.read_storage::<Energy>();
+        (&members, &initiative, &energy)
+            .join()
+            .any(|(.., e)| e.energy >= 0)
+    }
 
-            if should_delete {
-                to_delete.push(entity);
-            }
+    /// Picks the game back up after a Last Breath, with whoever is under control acting now if
+    /// they have the energy and after everyone else if not.
+    fn carry_on(&self) -> RunState {
+        if self.player_ready() {
+            RunState::AwaitingInput
+        } else {
+            RunState::MonsterTurn
         }
+    }
 
-        to_delete
+    /// Once everyone has spent their energy, a turn passes: status effects tick down (and
+    /// anything they did to hit points is applied), then everyone gains their speed in energy.
+    fn pass_turn(&mut self) {
+        let mut statuses = StatusEffectSystem {};
+        statuses.run_now(&self.ecs);
+        let mut damage = DamageSystem {};
+        damage.run_now(&self.ecs);
+        let mut energy = EnergySystem {};
+        energy.run_now(&self.ecs);
+        self.ecs.maintain();
     }
 
     fn goto_next_level(&mut self) {
-        // Delete entities that aren't the player or his/her equipment
-        let to_delete = self.entities_to_remove_on_level_change();
-        for target in to_delete {
-            self.ecs
-                .delete_entity(target)
-                .expect("Unable to delete entity");
-        }
+        self.change_level(1);
 
-        // Build a new map and place the player
-        let worldmap;
-        let current_depth;
-        {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            current_depth = worldmap_resource.depth;
-            *worldmap_resource = Map::new_map_rooms_and_corridors(current_depth + 1);
-            worldmap = worldmap_resource.clone();
-        }
-
-        // Spawn bad guys
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, current_depth + 1);
-        }
-
-        // Place the player and update resources
-        let (player_x, player_y) = worldmap.rooms[0].center();
-        let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
-        let mut position_components = self.ecs.write_storage::<Position>();
-        let player_entity = self.ecs.fetch::<Entity>();
-        let player_pos_comp = position_components.get_mut(*player_entity);
-        if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
+        // Notify the player and give the party some health
+        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        gamelog
+            .entries
+            .push("You descend to the next level, and take a moment to heal.".to_string());
+        let members = self.ecs.read_storage::<PartyMember>();
+        let mut health_store = self.ecs.write_storage::<CombatStats>();
+        for (_member, health) in (&members, &mut health_store).join() {
+            health.hp = i32::max(health.hp, health.max_hp / 2);
         }
+    }
 
-        // Mark the player's visibility as dirty
-        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(*player_entity);
-        if let Some(vs) = vs {
-            vs.dirty = true;
-        }
+    fn goto_previous_level(&mut self) {
+        self.change_level(-1);
 
-        // Notify the player and give them some health
         let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
         gamelog
             .entries
-            .push("You descend to the next level, and take a moment to heal.".to_string());
-        let mut player_health_store = self.ecs.write_storage::<CombatStats>();
-        let player_health = player_health_store.get_mut(*player_entity);
-        if let Some(player_health) = player_health {
-            player_health.hp = i32::max(player_health.hp, player_health.max_hp / 2);
+            .push("You climb back up to the previous level.".to_string());
+    }
+
+    /// Leaves the current level, remembering it and everything on it, for the level `offset`
+    /// steps further down.
+    fn change_level(&mut self, offset: i32) {
+        dungeon::freeze_level_entities(&mut self.ecs);
+
+        let current_depth;
+        {
+            let worldmap_resource = self.ecs.fetch::<Map>();
+            current_depth = worldmap_resource.depth;
+            let mut dungeon_master = self.ecs.write_resource::<MasterDungeonMap>();
+            dungeon_master.store_map(&worldmap_resource);
         }
+        self.generate_world_map(current_depth + offset, offset);
     }
 
     fn game_over_cleanup(&mut self) {
@@ -378,52 +585,135 @@ impl State {
             self.ecs.delete_entity(*del).expect("Deletion failed");
         }
 
-        // Build a new map and place the player
-        let worldmap;
+        // The next run gets a seed of its own
+        self.ecs.insert(RunSeed::random());
+    }
+
+    /// Starts a fresh run from `seed`: reseeds the game's dice, spawns the newly created party
+    /// (with the first of them under control) and builds the first level.
+    fn new_run(&mut self, seed: RunSeed) {
+        self.ecs.insert(seed);
+        self.ecs.insert(MasterDungeonMap::new());
+        self.ecs
+            .insert(rltk::RandomNumberGenerator::seeded(seed.seed));
         {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = Map::new_map_rooms_and_corridors(1);
-            worldmap = worldmap_resource.clone();
+            let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+            gamelog
+                .entries
+                .push(format!("This run's seed is {}.", seed.seed));
         }
 
-        // Spawn bad guys
-        for room in worldmap.rooms.iter().skip(1) {
-            spawner::spawn_room(&mut self.ecs, room, 1);
+        let party = self.ecs.fetch::<PartyDraft>().members.clone();
+        let mut members = Vec::new();
+        for (slot, character) in party.iter().enumerate() {
+            let member = spawner::party_member(&mut self.ecs, 0, 0, character, slot);
+            character::give_starting_equipment(&mut self.ecs, member, character.class);
+            members.push(member);
         }
+        party_system::take_control(&mut self.ecs, members[0]);
+        self.generate_world_map(1, 0);
+    }
+
+    /// Makes `new_depth` the current level, restoring it if the player has been there before.
+    /// `offset` is the direction of travel, so a returning player arrives on the right stairs,
+    /// with the rest of the party around them.
+    fn generate_world_map(&mut self, new_depth: i32, offset: i32) {
+        let stored_map = self.ecs.fetch::<MasterDungeonMap>().get_map(new_depth);
+        let player_start = if let Some(map) = stored_map {
+            let arrival = if offset < 0 {
+                TileType::DownStairs
+            } else {
+                TileType::UpStairs
+            };
+            let stairs_idx = map
+                .tiles
+                .iter()
+                .position(|tile| *tile == arrival)
+                .expect("Level has no stairs to arrive on") as i32;
+            let start = Position {
+                x: stairs_idx % map.width,
+                y: stairs_idx / map.width,
+            };
+            {
+                let mut worldmap_resource = self.ecs.write_resource::<Map>();
+                *worldmap_resource = map;
+            }
+            dungeon::thaw_level_entities(&mut self.ecs);
+            start
+        } else {
+            self.build_new_level(new_depth)
+        };
 
         // Place the player and update resources
-        let (player_x, player_y) = worldmap.rooms[0].center();
-        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
-        let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
-        let mut position_components = self.ecs.write_storage::<Position>();
-        let mut player_entity_writer = self.ecs.write_resource::<Entity>();
-        *player_entity_writer = player_entity;
-        let player_pos_comp = position_components.get_mut(player_entity);
-        if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
+        {
+            self.ecs.insert(DijkstraMaps::default());
+            let mut player_position = self.ecs.write_resource::<Point>();
+            *player_position = Point::new(player_start.x, player_start.y);
+            let mut position_components = self.ecs.write_storage::<Position>();
+            let player_entity = self.ecs.fetch::<Entity>();
+            let player_pos_comp = position_components.get_mut(*player_entity);
+            if let Some(player_pos_comp) = player_pos_comp {
+                player_pos_comp.x = player_start.x;
+                player_pos_comp.y = player_start.y;
+            }
+
+            // Mark the player's visibility as dirty
+            let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
+            let vs = viewshed_components.get_mut(*player_entity);
+            if let Some(vs) = vs {
+                vs.dirty = true;
+            }
+        }
+        party_system::gather(&mut self.ecs);
+    }
+
+    /// Generates a level nobody has visited yet, populates it and returns where the player
+    /// should arrive.
+    fn build_new_level(&mut self, new_depth: i32) -> Position {
+        let mut rng = self.ecs.fetch::<RunSeed>().rng_for_depth(new_depth);
+        let mut builder = map_builders::random_builder(new_depth, &mut rng);
+        builder.build_map(&mut rng);
+        let player_start = builder.get_starting_position();
+        {
+            let mut worldmap_resource = self.ecs.write_resource::<Map>();
+            *worldmap_resource = builder.get_map();
+            if new_depth > 1 {
+                let start_idx = worldmap_resource.xy_idx(player_start.x, player_start.y);
+                worldmap_resource.tiles[start_idx] = TileType::UpStairs;
+            }
+        }
+
+        // Hang the doors; deeper levels lock more of them
+        let map_width = self.ecs.fetch::<Map>().width;
+        for door_idx in builder.get_door_positions() {
+            let locked = rng.roll_dice(1, 20) <= i32::min(new_depth, 5);
+            let x = door_idx as i32 % map_width;
+            let y = door_idx as i32 / map_width;
+            spawner::door(&mut self.ecs, x, y, locked);
         }
 
-        // Mark the player's visibility as dirty
-        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(player_entity);
-        if let Some(vs) = vs {
-            vs.dirty = true;
+        // Spawn bad guys
+        for region in builder.get_spawn_regions().iter() {
+            spawner::spawn_region(&mut self.ecs, region, new_depth, &mut rng);
         }
+
+        player_start
     }
 }
 
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
+    let raw_master = raws::load_raws()?;
     let mut context = RltkBuilder::simple80x50()
         .with_title("Roguelike Tutorial")
         .build()?;
     context.with_post_scanlines(true);
     let mut gs = State { ecs: World::new() };
     gs.ecs.register::<Position>();
+    gs.ecs.register::<OtherLevelPosition>();
     gs.ecs.register::<Renderable>();
     gs.ecs.register::<Player>();
+    gs.ecs.register::<PartyMember>();
     gs.ecs.register::<Viewshed>();
     gs.ecs.register::<Monster>();
     gs.ecs.register::<Name>();
@@ -441,30 +731,59 @@ fn main() -> rltk::BError {
     gs.ecs.register::<WantsToPickupItem>();
     gs.ecs.register::<WantsToUseItem>();
     gs.ecs.register::<WantsToDropItem>();
-    gs.ecs.register::<Confusion>();
+    gs.ecs.register::<InflictsStatus>();
+    gs.ecs.register::<StatusEffects>();
+    gs.ecs.register::<Debilities>();
+    gs.ecs.register::<InflictsDebility>();
+    gs.ecs.register::<CuresDebilities>();
+    gs.ecs.register::<DeathsMark>();
+    gs.ecs.register::<Ammo>();
+    gs.ecs.register::<WantsToShoot>();
+    gs.ecs.register::<Initiative>();
+    gs.ecs.register::<Guarding>();
+    gs.ecs.register::<WantsToDelay>();
+    gs.ecs.register::<Energy>();
+    gs.ecs.register::<AiState>();
+    gs.ecs.register::<Morale>();
+    gs.ecs.register::<MakesNoise>();
+    gs.ecs.register::<Sneaking>();
+    gs.ecs.register::<Travelling>();
+    gs.ecs.register::<Faction>();
+    gs.ecs.register::<Reputation>();
+    gs.ecs.register::<NaturalRangedAttack>();
+    gs.ecs.register::<KnownSpell>();
+    gs.ecs.register::<TakingAim>();
+    gs.ecs.register::<Casting>();
     gs.ecs.register::<SimpleMarker<SerializeMe>>();
     gs.ecs.register::<SerializationHelper>();
     gs.ecs.register::<Equippable>();
     gs.ecs.register::<Equipped>();
     gs.ecs.register::<MeleePowerBonus>();
-    gs.ecs.register::<ArmorBonus>();
+    gs.ecs.register::<Armor>();
     gs.ecs.register::<WantsToRemoveItem>();
+    gs.ecs.register::<BlocksVisibility>();
+    gs.ecs.register::<Door>();
+    gs.ecs.register::<Locked>();
+    gs.ecs.register::<Key>();
+    gs.ecs.register::<Hidden>();
+    gs.ecs.register::<Trap>();
+    gs.ecs.register::<EntityMoved>();
+    gs.ecs.register::<WantsToSearch>();
+    gs.ecs.register::<Alerted>();
+    gs.ecs.register::<Class>();
+    gs.ecs.register::<Skills>();
+    gs.ecs.register::<Abilities>();
+    gs.ecs.register::<Experience>();
+    gs.ecs.register::<NaturalAttack>();
+    gs.ecs.register::<NaturalArmor>();
+    gs.ecs.register::<MonsterTags>();
+    gs.ecs.register::<Weapon>();
 
     gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-
-    let map: Map = Map::new_map_rooms_and_corridors(1);
-    let (player_x, player_y) = map.rooms[0].center();
+    gs.ecs.insert(raw_master);
 
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room, 1);
-    }
-
-    gs.ecs.insert(map);
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(player_entity);
+    gs.ecs.insert(Map::new(1, 80, 43));
+    gs.ecs.insert(Point::new(0, 0));
     gs.ecs.insert(RunState::MainMenu {
         menu_selection: gui::MainMenuSelection::NewGame,
     });
@@ -472,5 +791,15 @@ fn main() -> rltk::BError {
         entries: vec!["Welcome to Didactic Adventure".to_string()],
     });
 
+    // The run itself starts once a character has been made
+    let seed = RunSeed::from_args().unwrap_or_else(RunSeed::random);
+    gs.ecs.insert(seed);
+    gs.ecs
+        .insert(rltk::RandomNumberGenerator::seeded(seed.seed));
+    gs.ecs.insert(MasterDungeonMap::new());
+    gs.ecs.insert(DijkstraMaps::default());
+    gs.ecs.insert(CharacterDraft::new());
+    gs.ecs.insert(PartyDraft::default());
+
     rltk::main_loop(context, gs)
 }
@@ -0,0 +1,141 @@
+use super::super::{Map, Position, TileType};
+use super::{
+    generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant, MapBuilder,
+};
+use rltk::RandomNumberGenerator;
+
+/// Natural looking caves: start from noise and let a few rounds of "game of life" style
+/// smoothing pull it into caverns. There are no rooms, so spawning uses noise regions.
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_regions: Vec<Vec<usize>>,
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        self.spawn_regions.clone()
+    }
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_regions: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Some rolls of the noise leave no usable cave, so keep growing new ones until we get one
+        // with a floor near the middle and somewhere else reachable to put the exit.
+        let (start_idx, exit_tile) = loop {
+            self.grow_caves(rng);
+
+            // Start on the floor tile closest to the middle of the map
+            let start_idx = match self.nearest_floor(self.map.width / 2, self.map.height / 2) {
+                Some(idx) => idx,
+                None => continue,
+            };
+
+            // Find all tiles we can reach from the starting point, and put the exit furthest away
+            if let Some(exit_tile) =
+                remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx)
+            {
+                break (start_idx, exit_tile);
+            }
+        };
+        self.starting_position = Position {
+            x: start_idx as i32 % self.map.width,
+            y: start_idx as i32 / self.map.width,
+        };
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+
+        // Now we build noise regions for the spawner, keeping the player's tile clear
+        self.spawn_regions = generate_voronoi_spawn_regions(&self.map, rng);
+        for region in self.spawn_regions.iter_mut() {
+            region.retain(|idx| *idx != start_idx);
+        }
+    }
+
+    /// Fills the map with random noise, then smooths it into caverns.
+    fn grow_caves(&mut self, rng: &mut RandomNumberGenerator) {
+        // First we completely randomize the map, setting 55% of it to be floor.
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let roll = rng.roll_dice(1, 100);
+                let idx = self.map.xy_idx(x, y);
+                if roll > 55 {
+                    self.map.tiles[idx] = TileType::Floor
+                } else {
+                    self.map.tiles[idx] = TileType::Wall
+                }
+            }
+        }
+
+        // Now we iteratively apply cellular automata rules
+        for _i in 0..15 {
+            let mut newtiles = self.map.tiles.clone();
+
+            for y in 1..self.map.height - 1 {
+                for x in 1..self.map.width - 1 {
+                    let idx = self.map.xy_idx(x, y);
+                    let w = self.map.width as usize;
+                    let mut neighbors = 0;
+                    for n in [
+                        idx - 1,
+                        idx + 1,
+                        idx - w,
+                        idx + w,
+                        idx - (w - 1),
+                        idx - (w + 1),
+                        idx + (w - 1),
+                        idx + (w + 1),
+                    ]
+                    .iter()
+                    {
+                        if self.map.tiles[*n] == TileType::Wall {
+                            neighbors += 1;
+                        }
+                    }
+
+                    if neighbors > 4 || neighbors == 0 {
+                        newtiles[idx] = TileType::Wall;
+                    } else {
+                        newtiles[idx] = TileType::Floor;
+                    }
+                }
+            }
+
+            self.map.tiles = newtiles;
+        }
+    }
+
+    /// The floor tile closest to `(x, y)`, searching the whole map.
+    fn nearest_floor(&self, x: i32, y: i32) -> Option<usize> {
+        let width = self.map.width;
+        self.map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| **tile == TileType::Floor)
+            .map(|(idx, _)| idx)
+            .min_by_key(|idx| {
+                let (dx, dy) = (*idx as i32 % width - x, *idx as i32 / width - y);
+                dx * dx + dy * dy
+            })
+    }
+}
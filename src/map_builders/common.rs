@@ -0,0 +1,145 @@
+use super::super::{Map, Rect, TileType};
+use rltk::RandomNumberGenerator;
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.width as usize * map.height as usize {
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let idx = map.xy_idx(x, y);
+        if idx > 0 && idx < map.width as usize * map.height as usize {
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// Turns every room except the first (where the player starts) into a spawn region.
+pub fn rooms_to_spawn_regions(map: &Map, rooms: &[Rect]) -> Vec<Vec<usize>> {
+    let mut regions = Vec::new();
+    for room in rooms.iter().skip(1) {
+        let mut region = Vec::new();
+        for y in room.y1 + 1..=room.y2 {
+            for x in room.x1 + 1..=room.x2 {
+                region.push(map.xy_idx(x, y));
+            }
+        }
+        regions.push(region);
+    }
+    regions
+}
+
+/// Finds the places where corridors break through a room's walls, which is where doors go.
+pub fn find_doorways(map: &Map, rooms: &[Rect]) -> Vec<usize> {
+    let mut doors = Vec::new();
+    for room in rooms.iter() {
+        // The room's floor runs from x1 + 1 to x2, so its walls are at x1 and x2 + 1
+        let mut walls = Vec::new();
+        for x in room.x1..=room.x2 + 1 {
+            walls.push((x, room.y1));
+            walls.push((x, room.y2 + 1));
+        }
+        for y in room.y1 + 1..=room.y2 {
+            walls.push((room.x1, y));
+            walls.push((room.x2 + 1, y));
+        }
+
+        for (x, y) in walls {
+            if x < 1 || y < 1 || x > map.width - 2 || y > map.height - 2 {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor
+                && is_door_shaped(map, x, y)
+                && !doors.contains(&idx)
+            {
+                doors.push(idx);
+            }
+        }
+    }
+    doors
+}
+
+/// A doorway has wall on two opposite sides and open space on the other two.
+fn is_door_shaped(map: &Map, x: i32, y: i32) -> bool {
+    let open = |x: i32, y: i32| map.tiles[map.xy_idx(x, y)] != TileType::Wall;
+    (!open(x - 1, y) && !open(x + 1, y) && open(x, y - 1) && open(x, y + 1))
+        || (open(x - 1, y) && open(x + 1, y) && !open(x, y - 1) && !open(x, y + 1))
+}
+
+/// Walls off any floor the player can't reach from `start_idx`, and returns the reachable tile
+/// that is furthest away (a good spot for the exit). Returns `None` when nothing but the start
+/// itself can be reached, since there's then nowhere to put the exit.
+pub fn remove_unreachable_areas_returning_most_distant(
+    map: &mut Map,
+    start_idx: usize,
+) -> Option<usize> {
+    map.populate_blocked();
+    let map_starts: Vec<usize> = vec![start_idx];
+    // No path can be longer than the number of tiles, so nothing reachable gets cut off
+    let max_depth = (map.width * map.height) as f32;
+    let dijkstra_map = rltk::DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &map_starts,
+        map,
+        max_depth,
+    );
+    let mut exit_tile: Option<(usize, f32)> = None;
+    for (i, tile) in map.tiles.iter_mut().enumerate() {
+        if *tile == TileType::Floor {
+            let distance_to_start = dijkstra_map.map[i];
+            if distance_to_start == f32::MAX {
+                // We can't get to this tile - so we'll make it a wall
+                *tile = TileType::Wall;
+            } else if distance_to_start > exit_tile.map_or(0.0, |(_, best)| best) {
+                // If it is further away than our current exit candidate, move the exit
+                exit_tile = Some((i, distance_to_start));
+            }
+        }
+    }
+
+    exit_tile.map(|(idx, _)| idx)
+}
+
+/// Breaks an open map up into spawn regions using cellular (Voronoi) noise, so levels without
+/// rooms still get populated in clumps.
+pub fn generate_voronoi_spawn_regions(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+) -> Vec<Vec<usize>> {
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+    noise.set_noise_type(rltk::NoiseType::Cellular);
+    noise.set_frequency(0.08);
+    noise.set_cellular_distance_function(rltk::CellularDistanceFunction::Manhattan);
+
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] == TileType::Floor {
+                let cell_value_f = noise.get_noise(x as f32, y as f32) * 10240.0;
+                let cell_value = cell_value_f as i32;
+                noise_areas.entry(cell_value).or_default().push(idx);
+            }
+        }
+    }
+
+    noise_areas.into_values().collect()
+}
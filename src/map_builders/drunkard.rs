@@ -0,0 +1,129 @@
+use super::super::{Map, Position, TileType};
+use super::{
+    generate_voronoi_spawn_regions, remove_unreachable_areas_returning_most_distant, MapBuilder,
+};
+use rltk::RandomNumberGenerator;
+
+/// Drunkard's walk: send diggers staggering about from the centre until enough of the map is
+/// open. Produces winding, organic passages.
+pub struct DrunkardsWalkBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_regions: Vec<Vec<usize>>,
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        self.spawn_regions.clone()
+    }
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_regions: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        const DRUNKEN_LIFETIME: i32 = 400;
+        const FLOOR_PERCENT: f32 = 0.5;
+
+        // Set a central starting point
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.map.tiles[start_idx] = TileType::Floor;
+
+        let total_tiles = self.map.width * self.map.height;
+        let desired_floor_tiles = (FLOOR_PERCENT * total_tiles as f32) as usize;
+        let mut floor_tile_count = self.count_floor();
+        while floor_tile_count < desired_floor_tiles {
+            // Every digger after the first starts somewhere we have already dug
+            let (mut drunk_x, mut drunk_y) = if floor_tile_count == 1 {
+                (self.starting_position.x, self.starting_position.y)
+            } else {
+                let floors: Vec<usize> = self
+                    .map
+                    .tiles
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tile)| **tile == TileType::Floor)
+                    .map(|(idx, _)| idx)
+                    .collect();
+                let idx = floors[(rng.roll_dice(1, floors.len() as i32) - 1) as usize];
+                (idx as i32 % self.map.width, idx as i32 / self.map.width)
+            };
+
+            let mut drunk_life = DRUNKEN_LIFETIME;
+            while drunk_life > 0 {
+                let drunk_idx = self.map.xy_idx(drunk_x, drunk_y);
+                self.map.tiles[drunk_idx] = TileType::Floor;
+
+                match rng.roll_dice(1, 4) {
+                    1 => {
+                        if drunk_x > 2 {
+                            drunk_x -= 1;
+                        }
+                    }
+                    2 => {
+                        if drunk_x < self.map.width - 2 {
+                            drunk_x += 1;
+                        }
+                    }
+                    3 => {
+                        if drunk_y > 2 {
+                            drunk_y -= 1;
+                        }
+                    }
+                    _ => {
+                        if drunk_y < self.map.height - 2 {
+                            drunk_y += 1;
+                        }
+                    }
+                }
+
+                drunk_life -= 1;
+            }
+
+            floor_tile_count = self.count_floor();
+        }
+
+        // Find all tiles we can reach from the starting point, and put the exit furthest away
+        let exit_tile = remove_unreachable_areas_returning_most_distant(&mut self.map, start_idx)
+            .expect("the drunkards always dig away from the start");
+        self.map.tiles[exit_tile] = TileType::DownStairs;
+
+        // Now we build noise regions for the spawner, keeping the player's tile clear
+        self.spawn_regions = generate_voronoi_spawn_regions(&self.map, rng);
+        for region in self.spawn_regions.iter_mut() {
+            region.retain(|idx| *idx != start_idx);
+        }
+    }
+
+    fn count_floor(&self) -> usize {
+        self.map
+            .tiles
+            .iter()
+            .filter(|tile| **tile == TileType::Floor)
+            .count()
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Map, Position};
+use rltk::RandomNumberGenerator;
+mod bsp_dungeon;
+mod cellular_automata;
+mod common;
+mod drunkard;
+mod simple_map;
+use bsp_dungeon::BspDungeonBuilder;
+use cellular_automata::CellularAutomataBuilder;
+use common::*;
+use drunkard::DrunkardsWalkBuilder;
+use simple_map::SimpleMapBuilder;
+
+/// Everything the game needs from a level generator: the finished map, the areas the spawner
+/// should populate and where the player enters the level.
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>>;
+
+    /// Tiles that should get a door; only maps with rooms have any.
+    fn get_door_positions(&self) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+/// Picks one of the available map generation algorithms and a size for the level, using the
+/// level's own generator so the choice is reproducible from the run seed. Levels are at least a
+/// screen in size and get roomier the deeper you go.
+pub fn random_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> Box<dyn MapBuilder> {
+    let growth = i32::min(new_depth - 1, 10);
+    let width = rng.range(80, 81 + growth * 8);
+    let height = rng.range(43, 44 + growth * 4);
+    match rng.roll_dice(1, 4) {
+        1 => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+        2 => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        3 => Box::new(DrunkardsWalkBuilder::new(new_depth, width, height)),
+        _ => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TileType;
+    use super::*;
+
+    fn every_builder(width: i32, height: i32) -> Vec<Box<dyn MapBuilder>> {
+        vec![
+            Box::new(BspDungeonBuilder::new(1, width, height)),
+            Box::new(CellularAutomataBuilder::new(1, width, height)),
+            Box::new(DrunkardsWalkBuilder::new(1, width, height)),
+            Box::new(SimpleMapBuilder::new(1, width, height)),
+        ]
+    }
+
+    #[test]
+    fn every_builder_leaves_a_reachable_exit() {
+        for seed in 0..10 {
+            // The smallest levels, and the biggest
+            for (width, height) in [(80, 43), (160, 83)] {
+                for mut builder in every_builder(width, height) {
+                    builder.build_map(&mut RandomNumberGenerator::seeded(seed));
+                    let mut map = builder.get_map();
+                    map.populate_blocked();
+                    let start = builder.get_starting_position();
+                    let start_idx = map.xy_idx(start.x, start.y);
+                    let exit = map
+                        .tiles
+                        .iter()
+                        .position(|tile| *tile == TileType::DownStairs)
+                        .expect("the level has no exit");
+                    let max_depth = (map.width * map.height) as f32;
+                    let distances = rltk::DijkstraMap::new(
+                        map.width,
+                        map.height,
+                        &[start_idx],
+                        &map,
+                        max_depth,
+                    );
+                    assert!(distances.map[exit] < f32::MAX, "the exit can't be reached");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_long_way_round_is_still_reachable() {
+        // A corridor snaking back and forth across a big level, far longer than it is wide
+        let mut map = Map::new(1, 160, 83);
+        for y in (2..80).step_by(2) {
+            for x in 1..159 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+            if y < 78 {
+                let link = if y % 4 == 2 { 158 } else { 1 };
+                let idx = map.xy_idx(link, y + 1);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+        let floor_before = map.tiles.iter().filter(|t| **t == TileType::Floor).count();
+
+        let start_idx = map.xy_idx(1, 2);
+        let exit = remove_unreachable_areas_returning_most_distant(&mut map, start_idx);
+        let floor_after = map.tiles.iter().filter(|t| **t == TileType::Floor).count();
+        assert_eq!(floor_before, floor_after);
+        assert_eq!(exit, Some(map.xy_idx(158, 78)));
+    }
+}
@@ -0,0 +1,93 @@
+use super::super::{Map, Position, Rect, TileType};
+use super::{
+    apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel, find_doorways,
+    rooms_to_spawn_regions, MapBuilder,
+};
+use rltk::RandomNumberGenerator;
+
+/// Makes a new map using the algorithm from http://rogueliketutorials.com/tutorials/tcod/part-3/
+/// This gives a handful of random rooms and corridors joining them together.
+pub struct SimpleMapBuilder {
+    map: Map,
+    starting_position: Position,
+    rooms: Vec<Rect>,
+}
+
+impl MapBuilder for SimpleMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.rooms_and_corridors(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position.clone()
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        rooms_to_spawn_regions(&self.map, &self.rooms)
+    }
+
+    fn get_door_positions(&self) -> Vec<usize> {
+        find_doorways(&self.map, &self.rooms)
+    }
+}
+
+impl SimpleMapBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> SimpleMapBuilder {
+        SimpleMapBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            rooms: Vec::new(),
+        }
+    }
+
+    fn rooms_and_corridors(&mut self, rng: &mut RandomNumberGenerator) {
+        const MAX_ROOMS: i32 = 30;
+        const MIN_SIZE: i32 = 6;
+        const MAX_SIZE: i32 = 10;
+
+        for _i in 0..MAX_ROOMS {
+            let w = rng.range(MIN_SIZE, MAX_SIZE);
+            let h = rng.range(MIN_SIZE, MAX_SIZE);
+            let x = rng.roll_dice(1, self.map.width - w - 1) - 1;
+            let y = rng.roll_dice(1, self.map.height - h - 1) - 1;
+            let new_room = Rect::new(x, y, w, h);
+            let mut ok = true;
+            for other_room in self.rooms.iter() {
+                if new_room.intersect(other_room) {
+                    ok = false
+                }
+            }
+            if ok {
+                apply_room_to_map(&mut self.map, &new_room);
+
+                if !self.rooms.is_empty() {
+                    let (new_x, new_y) = new_room.center();
+                    let (prev_x, prev_y) = self.rooms[self.rooms.len() - 1].center();
+                    if rng.range(0, 2) == 1 {
+                        apply_horizontal_tunnel(&mut self.map, prev_x, new_x, prev_y);
+                        apply_vertical_tunnel(&mut self.map, prev_y, new_y, new_x);
+                    } else {
+                        apply_vertical_tunnel(&mut self.map, prev_y, new_y, prev_x);
+                        apply_horizontal_tunnel(&mut self.map, prev_x, new_x, new_y);
+                    }
+                }
+
+                self.rooms.push(new_room);
+            }
+        }
+
+        let stairs_position = self.rooms[self.rooms.len() - 1].center();
+        let stairs_idx = self.map.xy_idx(stairs_position.0, stairs_position.1);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+
+        let start_pos = self.rooms[0].center();
+        self.starting_position = Position {
+            x: start_pos.0,
+            y: start_pos.1,
+        };
+    }
+}
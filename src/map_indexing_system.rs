@@ -1,4 +1,4 @@
-use super::{BlocksTile, Map, Position};
+use super::{BlocksTile, BlocksVisibility, Door, Locked, Map, Position};
 use specs::prelude::*;
 
 pub struct MapIndexingSystem {}
@@ -8,14 +8,19 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        ReadStorage<'a, BlocksVisibility>,
+        ReadStorage<'a, Door>,
+        ReadStorage<'a, Locked>,
         Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, view_blockers, doors, locked, entities) = data;
 
         map.populate_blocked();
         map.clear_content_index();
+        map.view_blocked.clear();
+        map.closed_doors.clear();
         for (entity, position) in (&entities, &position).join() {
             let idx = map.xy_idx(position.x, position.y);
 
@@ -25,6 +30,16 @@ impl<'a> System<'a> for MapIndexingSystem {
                 map.blocked[idx] = true;
             }
 
+            // Closed doors (and anything else opaque) block line of sight
+            if view_blockers.get(entity).is_some() {
+                map.view_blocked.insert(idx);
+            }
+            if let Some(door) = doors.get(entity) {
+                if !door.open && locked.get(entity).is_none() {
+                    map.closed_doors.insert(idx);
+                }
+            }
+
             // Push the entity to the appropriate index slot. It's a Copy
             // type, so we don't need to clone it (we want to avoid moving it out of the ECS!)
             map.tile_content[idx].push(entity);
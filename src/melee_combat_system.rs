@@ -1,156 +1,597 @@
 use super::{
-    gamelog::GameLog, ArmorBonus, CombatStats, Equipped, MeleePowerBonus, Name, SufferDamage,
-    WantsToMelee,
+    character::{Ability, CON, DEX, STR},
+    gamelog::GameLog,
+    noise_system::COMBAT_NOISE,
+    roll::{roll_2d6, Outcome, BAD_MISS},
+    Abilities, Armor, CombatStats, Debilities, Debility, Energy, EntityMoved, Equipped, Experience,
+    Guarding, InflictsDebility, InflictsStatus, Initiative, MakesNoise, Map, MeleePowerBonus,
+    MonsterTag, MonsterTags, Name, NaturalArmor, NaturalAttack, PartyMember, Position,
+    StatusEffects, SufferDamage, Viewshed, WantsToMelee, Weapon, WeaponTag, ACTION_COST,
+    HEAVY_ATTACK_COST,
 };
+use rltk::{DiceType, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// Resolves melee. When the player starts the exchange they Hack and Slash (2d6+STR): 10+ hits,
+/// 7-9 hits but takes a hit back, 6- only takes the hit. When a monster starts it, the target
+/// Defends (2d6 plus the better of DEX and CON): 10+ avoids the blow, 7-9 takes half damage
+/// and 6- takes all of it.
+///
+/// Damage is the attacker's weapon die (or a monster's own, or a d4 for bare hands), plus its
+/// gear and, for monsters, STR, less the defender's armor. Weapon tags change things further:
+/// `Precise` attacks with DEX, `Messy` keeps the better of two damage rolls, `Piercing` ignores
+/// some armor and `Forceful` knocks the target back. Nobody can hit back at an attacker beyond
+/// their own weapon's reach, and a stunned or sleeping target can neither hit back nor defend.
+/// A monster with a status or debility to inflict passes it on whenever it does damage, and a
+/// roll of 3 or less leaves whoever made it with a debility. A target on guard strikes back at
+/// the first attacker in reach. During an encounter, blows land in initiative order.
+/// The player's attack costs them an action, or more with a two-handed weapon.
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         Entities<'a>,
+        ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, MeleePowerBonus>,
-        ReadStorage<'a, ArmorBonus>,
+        ReadStorage<'a, Armor>,
         ReadStorage<'a, Equipped>,
-        WriteExpect<'a, rltk::RandomNumberGenerator>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Abilities>,
+        WriteStorage<'a, Experience>,
+        ReadStorage<'a, NaturalAttack>,
+        ReadStorage<'a, NaturalArmor>,
+        ReadStorage<'a, MonsterTags>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Weapon>,
+        WriteExpect<'a, Map>,
+        WriteExpect<'a, Point>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, EntityMoved>,
+        // The lasting harm blows can do, and the party that can suffer it
+        (
+            ReadStorage<'a, InflictsStatus>,
+            WriteStorage<'a, StatusEffects>,
+            ReadStorage<'a, InflictsDebility>,
+            WriteStorage<'a, Debilities>,
+            ReadStorage<'a, PartyMember>,
+        ),
+        ReadStorage<'a, Initiative>,
+        WriteStorage<'a, Guarding>,
+        // What the attack costs, and the din it makes
+        (WriteStorage<'a, Energy>, WriteStorage<'a, MakesNoise>),
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             entities,
+            player_entity,
             mut log,
             mut wants_melee,
             names,
             combat_stats,
             mut inflict_damage,
             melee_power_bonuses,
-            defense_bonuses,
+            armor,
             equipped,
             mut rng,
+            abilities,
+            mut experience,
+            natural_attacks,
+            natural_armor,
+            monster_tags,
+            mut positions,
+            weapons,
+            mut map,
+            mut player_pos,
+            mut viewsheds,
+            mut entity_moved,
+            (inflicts_status, mut status_effects, inflicts_debility, mut debilities, members),
+            initiative,
+            mut guarding,
+            (mut energy, mut noises),
         ) = data;
 
-        for (entity, wants_melee, name, stats) in
-            (&entities, &wants_melee, &names, &combat_stats).join()
-        {
-            let target_stats = combat_stats.get(wants_melee.target).unwrap();
-            let mut offensive_bonus = 0;
-            for (_item_entity, melee_bonus, equipped_by) in
-                (&entities, &melee_power_bonuses, &equipped).join()
-            {
-                if equipped_by.owner == entity {
-                    offensive_bonus += melee_bonus.bonus;
+        let offensive_bonus = |attacker: Entity| -> i32 {
+            let mut bonus = 0;
+            for (melee_bonus, equipped_by) in (&melee_power_bonuses, &equipped).join() {
+                if equipped_by.owner == attacker {
+                    bonus += melee_bonus.bonus;
                 }
             }
-            if stats.hp > 0 && target_stats.hp > 0 {
-                let target_name = names.get(wants_melee.target).unwrap();
-                let dice_roll = rng.roll_dice(2, 6) + stats.str;
-                let mut defensive_bonus = 0;
-                for (_item_entity, defense_bonus, equipped_by) in
-                    (&entities, &defense_bonuses, &equipped).join()
-                {
-                    if equipped_by.owner == wants_melee.target {
-                        defensive_bonus += defense_bonus.bonus;
-                    }
-                }
+            if Abilities::has(&abilities, attacker, Ability::WeaponMaster) {
+                bonus += 1;
+            }
+            bonus
+        };
+        let defensive_bonus = |defender: Entity| -> i32 {
+            let mut bonus = Armor::total(&armor, &equipped, defender);
+            if Abilities::has(&abilities, defender, Ability::Armored) {
+                bonus += 1;
+            }
+            if let Some(armor) = natural_armor.get(defender) {
+                bonus += armor.armor;
+            }
+            bonus
+        };
+        // A horde hits harder for each of its members crowding the same target
+        let packmates = |attacker: Entity, target: Entity| -> i32 {
+            if !MonsterTags::has(&monster_tags, attacker, MonsterTag::Horde) {
+                return 0;
+            }
+            let target_pos = match positions.get(target) {
+                Some(pos) => pos,
+                None => return 0,
+            };
+            (&entities, &positions)
+                .join()
+                .filter(|(other, pos)| {
+                    *other != attacker
+                        && MonsterTags::has(&monster_tags, *other, MonsterTag::Horde)
+                        && i32::abs(pos.x - target_pos.x) <= 1
+                        && i32::abs(pos.y - target_pos.y) <= 1
+                })
+                .count() as i32
+        };
+        // How far away `entity` can hit things
+        let reach = |entity: Entity| -> i32 {
+            Weapon::wielded_by(&weapons, &equipped, entity).map_or(1, |weapon| weapon.range())
+        };
+        let distance = |a: Entity, b: Entity| -> i32 {
+            match (positions.get(a), positions.get(b)) {
+                (Some(a), Some(b)) => i32::max(i32::abs(a.x - b.x), i32::abs(a.y - b.y)),
+                _ => 1,
+            }
+        };
+        let damage_roll = |rng: &mut RandomNumberGenerator, attacker: Entity, target: Entity| {
+            let weapon = Weapon::wielded_by(&weapons, &equipped, attacker);
+            let dice = match (weapon, natural_attacks.get(attacker)) {
+                (Some(weapon), _) => weapon.damage,
+                (None, Some(attack)) => attack.damage,
+                (None, None) => DiceType::new(1, 4, 0),
+            };
+            let mut roll = rng.roll(dice);
+            if weapon.is_some_and(|w| w.has(WeaponTag::Messy)) {
+                roll = i32::max(roll, rng.roll(dice));
+            }
+            let piercing = weapon.map_or(0, |w| w.piercing());
+            let str_bonus = if attacker == *player_entity {
+                // The player's STR goes into their attack roll instead
+                0
+            } else {
+                combat_stats.get(attacker).map_or(0, |stats| stats.str)
+            };
+            let damage = roll + str_bonus + offensive_bonus(attacker) + packmates(attacker, target)
+                - i32::max(0, defensive_bonus(target) - piercing);
+            i32::max(0, damage)
+        };
+        let forceful = |attacker: Entity| -> bool {
+            Weapon::wielded_by(&weapons, &equipped, attacker)
+                .is_some_and(|weapon| weapon.has(WeaponTag::Forceful))
+        };
+
+        // Which of the party dealt a blow, so kills earn them experience
+        let party_slot = |entity: Entity| members.get(entity).map(|member| member.slot);
 
-                match dice_roll {
-                    12 => {
-                        // critical success
-                        let damage = rng.roll_dice(1, 6) + offensive_bonus - defensive_bonus;
-                        let damage = damage + 2; // TODO figure out a better boon
+        let helpless = |entity: Entity| -> bool {
+            status_effects
+                .get(entity)
+                .is_some_and(|statuses| statuses.helpless())
+        };
+
+        let mut knockbacks: Vec<(Entity, Entity)> = Vec::new();
+        // Attacker, target and damage of every blow that landed
+        let mut hits: Vec<(Entity, Entity, i32)> = Vec::new();
+        // Lasting harm from badly missed rolls, with how it came about
+        let mut bad_misses: Vec<(Entity, Debility, &str)> = Vec::new();
+
+        // Guards that have already struck back
+        let mut countered: Vec<Entity> = Vec::new();
+
+        let order = Initiative::turn_order(&entities, &initiative, *player_entity);
+        let mut attacks: Vec<_> = (&entities, &wants_melee, &names, &combat_stats)
+            .join()
+            .collect();
+        attacks.sort_by_key(|(entity, ..)| {
+            order
+                .iter()
+                .position(|e| e == entity)
+                .unwrap_or(order.len())
+        });
+        for (entity, wants_melee, name, stats) in attacks {
+            let target = wants_melee.target;
+            let target_stats = combat_stats.get(target).unwrap();
+            if stats.hp < 1 || target_stats.hp < 1 {
+                continue;
+            }
+            let target_name = names.get(target).unwrap();
+            MakesNoise::make(&mut noises, entity, COMBAT_NOISE);
+
+            if entity == *player_entity {
+                let heavy = Weapon::wielded_by(&weapons, &equipped, entity)
+                    .is_some_and(|weapon| weapon.has(WeaponTag::TwoHanded));
+                let cost = if heavy {
+                    HEAVY_ATTACK_COST
+                } else {
+                    ACTION_COST
+                };
+                Energy::spend(&mut energy, entity, cost);
+
+                // Hack and Slash
+                let small = if MonsterTags::has(&monster_tags, target, MonsterTag::Small) {
+                    -1
+                } else {
+                    0
+                };
+                let precise = Weapon::wielded_by(&weapons, &equipped, entity)
+                    .is_some_and(|weapon| weapon.has(WeaponTag::Precise));
+                let stat = if precise {
+                    stats.dex
+                        + Armor::heavy_penalty(&armor, &equipped, entity)
+                        + Debilities::penalty(&debilities, entity, DEX)
+                } else {
+                    stats.str + Debilities::penalty(&debilities, entity, STR)
+                };
+                let (dice_roll, outcome) = roll_2d6(&mut rng, stat + small);
+                if dice_roll <= BAD_MISS {
+                    bad_misses.push((
+                        entity,
+                        Debility::Shaky,
+                        "You overreach, and wrench something.",
+                    ));
+                }
+                let can_hit_back = distance(entity, target) <= reach(target) && !helpless(target);
+                let mut damage = damage_roll(&mut rng, entity, target);
+                match outcome {
+                    Outcome::Success => {
+                        if dice_roll >= 12 {
+                            // critical success
+                            damage += 2; // TODO figure out a better boon
+                        }
                         log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
+                            "{} attacks {}, hitting for {} hp.",
                             &name.name, &target_name.name, damage
                         ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            target,
+                            damage,
+                            party_slot(entity),
+                        );
+                        hits.push((entity, target, damage));
+                        if forceful(entity) {
+                            knockbacks.push((entity, target));
+                        }
                     }
-                    10 | 11 => {
-                        // normal success
-                        let damage = rng.roll_dice(1, 6) + offensive_bonus - defensive_bonus;
+                    Outcome::Partial => {
+                        // partial failure ... enemy gets a hit too
                         log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
+                            "{} attacks {}, hitting for {} hp.",
                             &name.name, &target_name.name, damage
                         ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            target,
+                            damage,
+                            party_slot(entity),
+                        );
+                        hits.push((entity, target, damage));
+                        if can_hit_back {
+                            let counterattack = damage_roll(&mut rng, target, entity);
+                            log.entries.push(format!(
+                                "{} hits back, for {} hp.",
+                                &target_name.name, counterattack
+                            ));
+                            SufferDamage::new_damage(
+                                &mut inflict_damage,
+                                entity,
+                                counterattack,
+                                None,
+                            );
+                            hits.push((target, entity, counterattack));
+                        } else if helpless(target) {
+                            log.entries
+                                .push(format!("{} is in no state to hit back.", &target_name.name));
+                        } else {
+                            log.entries
+                                .push(format!("{} can't reach to hit back.", &target_name.name));
+                        }
                     }
-                    7..=9 => {
-                        // partial failure ... enemy gets a hit too
-                        let damage = rng.roll_dice(1, 6) + offensive_bonus - defensive_bonus;
+                    Outcome::Miss => {
+                        // botch, enemy get a free hit - but a miss is a lesson learned
+                        Experience::mark(&mut experience, entity, 1);
                         log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &name.name, &target_name.name, damage
+                            "{} attacks {}, but is unable to hurt them.",
+                            &name.name, &target_name.name
                         ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
-
-                        // enemy's hit - reverse the calculations
-                        let mut offensive_bonus = 0;
-                        for (_item_entity, melee_bonus, equipped_by) in
-                            (&entities, &melee_power_bonuses, &equipped).join()
-                        {
-                            if equipped_by.owner == wants_melee.target {
-                                offensive_bonus += melee_bonus.bonus;
-                            }
-                        }
-
-                        let mut defensive_bonus = 0;
-                        for (_item_entity, defense_bonus, equipped_by) in
-                            (&entities, &defense_bonuses, &equipped).join()
-                        {
-                            if equipped_by.owner == entity {
-                                defensive_bonus += defense_bonus.bonus;
-                            }
+                        if can_hit_back {
+                            let counterattack = damage_roll(&mut rng, target, entity);
+                            log.entries.push(format!(
+                                "{} hits back, for {} hp.",
+                                &target_name.name, counterattack
+                            ));
+                            SufferDamage::new_damage(
+                                &mut inflict_damage,
+                                entity,
+                                counterattack,
+                                None,
+                            );
+                            hits.push((target, entity, counterattack));
+                        } else if helpless(target) {
+                            log.entries
+                                .push(format!("{} is in no state to hit back.", &target_name.name));
+                        } else {
+                            log.entries
+                                .push(format!("{} can't reach to hit back.", &target_name.name));
                         }
-
-                        let damage = rng.roll_dice(1, 6) + offensive_bonus - defensive_bonus;
+                    }
+                }
+            } else {
+                // Defend
+                let damage = damage_roll(&mut rng, entity, target);
+                let dex = target_stats.dex
+                    + Armor::heavy_penalty(&armor, &equipped, target)
+                    + Debilities::penalty(&debilities, target, DEX);
+                let con = target_stats.con + Debilities::penalty(&debilities, target, CON);
+                let dodging = dex >= con;
+                let outcome = if helpless(target) {
+                    Outcome::Miss
+                } else {
+                    let (dice_roll, outcome) = roll_2d6(&mut rng, i32::max(dex, con));
+                    if dice_roll <= BAD_MISS {
+                        bad_misses.push((
+                            target,
+                            Debility::Stunned,
+                            "The blow leaves you reeling.",
+                        ));
+                    }
+                    outcome
+                };
+                match outcome {
+                    Outcome::Success => {
+                        let how = if dodging {
+                            "dodges out of the way"
+                        } else {
+                            "shrugs off the blow"
+                        };
                         log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &target_name.name, &name.name, damage
+                            "{} attacks {}, who {}.",
+                            &name.name, &target_name.name, how
                         ));
-                        SufferDamage::new_damage(&mut inflict_damage, entity, damage);
                     }
-                    _ => {
-                        // botch, enemy get a free hit
+                    Outcome::Partial => {
+                        let damage = damage / 2;
                         log.entries.push(format!(
-                            "{} is unable to hurt {}",
-                            &name.name, &target_name.name
+                            "{} attacks {}, who only half avoids it, taking {} hp.",
+                            &name.name, &target_name.name, damage
                         ));
-
-                        // enemy's hit - reverse the calculations
-                        let mut offensive_bonus = 0;
-                        for (_item_entity, melee_bonus, equipped_by) in
-                            (&entities, &melee_power_bonuses, &equipped).join()
-                        {
-                            if equipped_by.owner == wants_melee.target {
-                                offensive_bonus += melee_bonus.bonus;
-                            }
+                        SufferDamage::new_damage(&mut inflict_damage, target, damage, None);
+                        hits.push((entity, target, damage));
+                    }
+                    Outcome::Miss => {
+                        Experience::mark(&mut experience, target, 1);
+                        log.entries.push(format!(
+                            "{} attacks {}, hitting for {} hp.",
+                            &name.name, &target_name.name, damage
+                        ));
+                        SufferDamage::new_damage(&mut inflict_damage, target, damage, None);
+                        hits.push((entity, target, damage));
+                        if forceful(entity) {
+                            knockbacks.push((entity, target));
                         }
+                    }
+                }
 
-                        let mut defensive_bonus = 0;
-                        for (_item_entity, defense_bonus, equipped_by) in
-                            (&entities, &defense_bonuses, &equipped).join()
-                        {
-                            if equipped_by.owner == entity {
-                                defensive_bonus += defense_bonus.bonus;
-                            }
-                        }
+                if guarding.get(target).is_some()
+                    && !countered.contains(&target)
+                    && !helpless(target)
+                    && distance(entity, target) <= reach(target)
+                {
+                    countered.push(target);
+                    let counterattack = damage_roll(&mut rng, target, entity);
+                    log.entries.push(format!(
+                        "{} is on guard, and strikes back for {} hp.",
+                        &target_name.name, counterattack
+                    ));
+                    SufferDamage::new_damage(
+                        &mut inflict_damage,
+                        entity,
+                        counterattack,
+                        party_slot(target),
+                    );
+                    hits.push((target, entity, counterattack));
+                }
+            }
+        }
 
-                        let damage = rng.roll_dice(1, 6) + offensive_bonus - defensive_bonus;
-                        log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &target_name.name, &name.name, damage
-                        ));
-                        SufferDamage::new_damage(&mut inflict_damage, entity, damage);
+        for guard in countered {
+            guarding.remove(guard);
+        }
+
+        for (attacker, target, damage) in hits {
+            if damage < 1 {
+                continue;
+            }
+            if let Some(inflicts) = inflicts_status.get(attacker) {
+                StatusEffects::add(&mut status_effects, target, inflicts.status, inflicts.turns);
+                let status = inflicts.status.name().to_lowercase();
+                if target == *player_entity {
+                    log.entries.push(format!("You are {}!", status));
+                } else if let Some(name) = names.get(target) {
+                    log.entries.push(format!("{} is {}!", name.name, status));
+                }
+            }
+            if let Some(inflicts) = inflicts_debility.get(attacker) {
+                if Debilities::inflict(&mut debilities, target, inflicts.debility) {
+                    let debility = inflicts.debility.describe();
+                    if target == *player_entity {
+                        log.entries.push(format!("You are left {}.", debility));
+                    } else if let (Some(_), Some(name)) = (members.get(target), names.get(target)) {
+                        log.entries
+                            .push(format!("{} is left {}.", name.name, debility));
                     }
                 }
             }
         }
 
+        for (victim, debility, how) in bad_misses {
+            if members.get(victim).is_none()
+                || !Debilities::inflict(&mut debilities, victim, debility)
+            {
+                continue;
+            }
+            if victim == *player_entity {
+                log.entries
+                    .push(format!("{} You are {}.", how, debility.describe()));
+            } else if let Some(name) = names.get(victim) {
+                log.entries
+                    .push(format!("{} is left {}.", name.name, debility.describe()));
+            }
+        }
+
+        for (attacker, target) in knockbacks {
+            let (from, to) = match (positions.get(attacker), positions.get(target)) {
+                (Some(from), Some(to)) => (from.clone(), to.clone()),
+                _ => continue,
+            };
+            let x = to.x + i32::signum(to.x - from.x);
+            let y = to.y + i32::signum(to.y - from.y);
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            // Walls, closed doors and anyone standing behind the target stop it dead
+            let idx = map.xy_idx(x, y);
+            if map.blocked[idx] {
+                continue;
+            }
+            let old_idx = map.xy_idx(to.x, to.y);
+            map.blocked[idx] = map.blocked[old_idx];
+            map.blocked[old_idx] = false;
+            if let Some(pos) = positions.get_mut(target) {
+                pos.x = x;
+                pos.y = y;
+            }
+            if let Some(viewshed) = viewsheds.get_mut(target) {
+                viewshed.dirty = true;
+            }
+            if target == *player_entity {
+                player_pos.x = x;
+                player_pos.y = y;
+                entity_moved
+                    .insert(target, EntityMoved {})
+                    .expect("Unable to insert marker");
+            }
+            if let Some(name) = names.get(target) {
+                log.entries.push(format!("{} is knocked back!", name.name));
+            }
+        }
+
         wants_melee.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ArmorKind, EquipmentSlot};
+    use super::*;
+    use rltk::RandomNumberGenerator;
+
+    fn stats(str: i32, defence: i32) -> CombatStats {
+        CombatStats {
+            str,
+            dex: defence,
+            con: defence,
+            int: 0,
+            wis: 0,
+            cha: 0,
+            max_hp: 100,
+            hp: 100,
+        }
+    }
+
+    /// A monster swings at the player, who defends with `defence` in both DEX and CON.
+    /// Returns the damage the player takes.
+    fn defend(seed: u64, defence: i32) -> i32 {
+        defend_with(seed, defence, vec![], 0)
+    }
+
+    /// As `defend`, with the player's abilities and the points of the armor they're wearing.
+    fn defend_with(seed: u64, defence: i32, abilities: Vec<Ability>, armor: i32) -> i32 {
+        let mut world = World::new();
+        let mut melee = MeleeCombatSystem {};
+        System::setup(&mut melee, &mut world);
+        world.insert(GameLog { entries: vec![] });
+        world.insert(RandomNumberGenerator::seeded(seed));
+        world.insert(Map::new(1, 80, 43));
+        world.insert(Point::new(0, 0));
+        let player = world
+            .create_entity()
+            .with(stats(0, defence))
+            .with(Name {
+                name: "Player".to_string(),
+            })
+            .build();
+        world.insert(player);
+        world
+            .write_storage::<Abilities>()
+            .insert(player, Abilities { abilities })
+            .expect("Unable to insert abilities");
+        world
+            .create_entity()
+            .with(Armor {
+                kind: ArmorKind::Light,
+                points: armor,
+            })
+            .with(Equipped {
+                owner: player,
+                slot: EquipmentSlot::Body,
+            })
+            .build();
+        world
+            .create_entity()
+            .with(stats(6, 0))
+            .with(Name {
+                name: "Orc".to_string(),
+            })
+            .with(WantsToMelee { target: player })
+            .build();
+        melee.run_now(&world);
+        let hurt = world.read_storage::<SufferDamage>();
+        let taken = hurt
+            .get(player)
+            .map_or(0, |hurt| hurt.amount.iter().map(|(amount, _)| amount).sum());
+        taken
+    }
+
+    #[test]
+    fn defending_takes_all_half_or_none_of_the_blow() {
+        for seed in 0..50 {
+            // The same seed rolls the same blow and the same 2d6, so raising the defence walks
+            // the total through 6-, 7-9 and 10+ in that order.
+            let taken: Vec<i32> = (-12..=10).map(|defence| defend(seed, defence)).collect();
+            let full = taken[0];
+            assert!(full >= 7, "the orc always hits for d6+6");
+            let partial = taken.iter().filter(|&&t| t == full / 2).count();
+            let misses = taken.iter().take_while(|&&t| t == full).count();
+            assert_eq!(partial, 3, "7-9 is three totals wide");
+            assert!(taken[misses..misses + 3].iter().all(|&t| t == full / 2));
+            assert!(taken[misses + 3..].iter().all(|&t| t == 0));
+            assert_eq!(*taken.last().unwrap(), 0, "+10 can't roll under 10");
+        }
+    }
+
+    #[test]
+    fn armor_and_armored_soak_up_the_blow() {
+        for seed in 0..20 {
+            let full = defend(seed, -12);
+            assert_eq!(defend_with(seed, -12, vec![Ability::Armored], 0), full - 1);
+            assert_eq!(defend_with(seed, -12, vec![], 2), full - 2);
+            assert_eq!(defend_with(seed, -12, vec![Ability::Armored], 2), full - 3);
+        }
+    }
+}
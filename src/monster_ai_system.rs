@@ -1,14 +1,49 @@
-use super::{Confusion, Map, Monster, Position, RunState, Viewshed, WantsToMelee};
-use rltk::Point;
+use super::{
+    dijkstra_maps::DijkstraMaps,
+    gamelog::GameLog,
+    noise_system::DOOR_NOISE,
+    raws::RawMaster,
+    roll::{roll_2d6, Outcome},
+    AiState, Alerted, BlocksTile, BlocksVisibility, Casting, CombatStats, Door, Energy, Faction,
+    Initiative, KnownSpell, MakesNoise, Map, Monster, MonsterTag, MonsterTags, Morale, Name,
+    NaturalRangedAttack, PartyMember, Position, Ranged, Reaction, Renderable, Reputation, RunState,
+    Status, StatusEffects, TakingAim, Viewshed, WantsToDelay, WantsToMelee, WantsToShoot,
+    WantsToUseItem, ACTION_COST,
+};
+use rltk::{BaseMap, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// How close an idle monster has to be to notice the player.
+const IDLE_NOTICE_RANGE: i32 = 3;
+/// How long a monster that loses its nerve falls back for.
+const FALL_BACK_TURNS: i32 = 3;
+/// How long a routed monster runs for.
+const ROUT_TURNS: i32 = 20;
+/// How close a monster that fights from afar lets its quarry get before backing off.
+const KEEP_DISTANCE: f32 = 3.0;
+
+/// Moves the monsters and picks their fights. In an encounter they act in initiative order,
+/// starting with whoever comes after the player and stopping at the next of the party still to
+/// act; a player who delays only lets the rest of the round go, then drops to the bottom of the
+/// order. Monsters outside the encounter act after.
+/// Only monsters with the energy to act get a go, and each go costs them an action.
+///
+/// Each monster has an `AiState`. Who it hunts and who it runs from is up to its `Faction`'s
+/// reactions. Idle monsters stay put until something comes close; wandering ones roam and notice
+/// anything in view. Either way, once they notice their quarry they hunt it down, and if they
+/// lose sight of it they search where it was last seen (or where something was heard). Anything
+/// they fear sends them running. The first time a monster is badly hurt it tests its `Morale`
+/// and may flee. Hunting and fleeing the player follow the shared `DijkstraMaps`.
+///
+/// Monsters that can shoot or cast keep their distance, and give fair warning: they take aim or
+/// start a spell on one go and let it loose on the next, if their quarry is still in reach. A
+/// spell then waits out its cooldown before it can be cast again.
 pub struct MonsterAI {}
 
 impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         WriteExpect<'a, Map>,
-        ReadExpect<'a, Point>,
         ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
         Entities<'a>,
@@ -16,67 +51,525 @@ impl<'a> System<'a> for MonsterAI {
         ReadStorage<'a, Monster>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
-        WriteStorage<'a, Confusion>,
+        ReadStorage<'a, StatusEffects>,
+        // For opening doors
+        (
+            WriteStorage<'a, Door>,
+            WriteStorage<'a, BlocksTile>,
+            WriteStorage<'a, BlocksVisibility>,
+            WriteStorage<'a, Renderable>,
+        ),
+        WriteStorage<'a, Alerted>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, MonsterTags>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, WantsToDelay>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, AiState>,
+        WriteStorage<'a, Morale>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, MakesNoise>,
+        ReadExpect<'a, DijkstraMaps>,
+        // Who's on whose side
+        (
+            ReadExpect<'a, RawMaster>,
+            ReadStorage<'a, Faction>,
+            ReadStorage<'a, Reputation>,
+            ReadStorage<'a, PartyMember>,
+        ),
+        // For attacking from afar
+        (
+            ReadStorage<'a, NaturalRangedAttack>,
+            WriteStorage<'a, KnownSpell>,
+            ReadStorage<'a, Ranged>,
+            WriteStorage<'a, TakingAim>,
+            WriteStorage<'a, Casting>,
+            WriteStorage<'a, WantsToShoot>,
+            WriteStorage<'a, WantsToUseItem>,
+        ),
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut map,
-            player_pos,
             player_entity,
             runstate,
             entities,
-            mut viewshed,
+            mut viewsheds,
             monster,
-            mut position,
+            mut positions,
             mut wants_to_melee,
-            mut confused,
+            status_effects,
+            (mut doors, mut blocks_movement, mut blocks_visibility, mut renderables),
+            mut alerted,
+            combat_stats,
+            monster_tags,
+            mut rng,
+            mut initiative,
+            mut wants_delay,
+            mut energy,
+            mut ai_states,
+            mut morale,
+            names,
+            mut log,
+            mut noises,
+            dijkstra_maps,
+            (raws, factions, reputations, members),
+            (
+                natural_ranged,
+                mut known_spells,
+                ranged,
+                mut taking_aim,
+                mut casting,
+                mut wants_shoot,
+                mut wants_use,
+            ),
         ) = data;
 
         if *runstate != RunState::MonsterTurn {
             return;
         }
 
-        for (entity, mut viewshed, _monster, mut pos) in
-            (&entities, &mut viewshed, &monster, &mut position).join()
-        {
-            let mut can_act = true;
+        let order = Initiative::turn_order(&entities, &initiative, *player_entity);
+        let delaying = wants_delay.get(*player_entity).is_some();
+        let mut acting: Vec<Entity> = match order.iter().position(|e| *e == *player_entity) {
+            Some(i) if delaying => order[i + 1..].to_vec(),
+            Some(i) => order[i + 1..]
+                .iter()
+                .chain(order[..i].iter())
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        };
+        acting.retain(|e| Energy::ready(&energy, *e));
+        // The rest of the party take their own turns, so the monsters' go ends at the next of them
+        if let Some(next) = acting.iter().position(|e| monster.get(*e).is_none()) {
+            acting.truncate(next);
+        }
+        if delaying {
+            let last = order
+                .last()
+                .and_then(|e| initiative.get(*e))
+                .map(|i| i.roll);
+            if let (Some(last), Some(player)) = (last, initiative.get_mut(*player_entity)) {
+                player.roll = i32::min(player.roll, last - 1);
+            }
+            wants_delay.clear();
+        } else {
+            acting.extend(
+                (&entities, &monster, !&initiative)
+                    .join()
+                    .map(|(entity, ..)| entity)
+                    .filter(|entity| Energy::ready(&energy, *entity)),
+            );
+        }
+
+        let mut doors_opened = false;
+        for entity in acting {
+            // Whatever it does, even losing the turn, takes an action
+            Energy::spend(&mut energy, entity, ACTION_COST);
 
-            let is_confused = confused.get_mut(entity);
-            if let Some(i_am_confused) = is_confused {
-                i_am_confused.turns -= 1;
-                if i_am_confused.turns < 1 {
-                    confused.remove(entity);
+            // Whatever it readied last go has to be let loose now or not at all, and its spells
+            // come a go closer to being ready again
+            let aimed = taking_aim.remove(entity).is_some();
+            let readied_spell = casting.remove(entity).map(|c| c.spell);
+            for known in (&mut known_spells).join() {
+                if known.caster == entity {
+                    known.ready_in = i32::max(0, known.ready_in - 1);
                 }
-                can_act = false;
             }
 
-            if can_act {
-                let distance =
-                    rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-                if distance < 1.5 {
-                    // TODO: should inject a Defend roll onto the target
-                    wants_to_melee
-                        .insert(*player_entity, WantsToMelee { target: entity })
-                        .expect("Unable to insert attack");
-                } else if viewshed.visible_tiles.contains(&*player_pos) {
-                    // Path to the player
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y),
-                        map.xy_idx(player_pos.x, player_pos.y),
-                        &*map,
-                    );
-                    if path.success && path.steps.len() > 1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
+            // The nearest creature in view it would attack, and the nearest it would run from,
+            // with how far away each is
+            let mut quarry: Option<(Entity, Point, f32)> = None;
+            let mut threat: Option<(Entity, Point, f32)> = None;
+            if let (Some(viewshed), Some(pos)) = (viewsheds.get(entity), positions.get(entity)) {
+                let here = Point::new(pos.x, pos.y);
+                for (other, other_pos, stats) in (&entities, &positions, &combat_stats).join() {
+                    let there = Point::new(other_pos.x, other_pos.y);
+                    if other == entity || stats.hp < 1 || !viewshed.visible_tiles.contains(&there) {
+                        continue;
+                    }
+                    let distance = rltk::DistanceAlg::Pythagoras.distance2d(here, there);
+                    let sighting = Some((other, there, distance));
+                    match Faction::reaction(&raws, &factions, &reputations, entity, other) {
+                        Reaction::Attack if quarry.is_none_or(|(.., d)| distance < d) => {
+                            quarry = sighting
+                        }
+                        Reaction::Flee if threat.is_none_or(|(.., d)| distance < d) => {
+                            threat = sighting
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            let (viewshed, pos) = match (viewsheds.get_mut(entity), positions.get_mut(entity)) {
+                (Some(viewshed), Some(pos)) if monster.get(entity).is_some() => (viewshed, pos),
+                _ => continue,
+            };
+            let statuses = status_effects.get(entity);
+            let can_act = statuses.is_none_or(|s| !s.helpless());
+
+            if can_act && statuses.is_some_and(|s| s.has(Status::Confused)) {
+                // Stumble off somewhere at random, though too addled to work a door, so only
+                // into open ground
+                let my_idx = map.xy_idx(pos.x, pos.y);
+                let exits: Vec<(usize, f32)> = map
+                    .get_available_exits(my_idx)
+                    .into_iter()
+                    .filter(|(exit, _)| !map.blocked[*exit])
+                    .collect();
+                if !exits.is_empty() {
+                    let (exit, _cost) = exits[(rng.roll_dice(1, exits.len() as i32) - 1) as usize];
+                    map.blocked[my_idx] = false;
+                    pos.x = exit as i32 % map.width;
+                    pos.y = exit as i32 / map.width;
+                    map.blocked[exit] = true;
+                    viewshed.dirty = true;
+                }
+            } else if can_act {
+                let here = Point::new(pos.x, pos.y);
+                let mut state = ai_states.get(entity).copied().unwrap_or(AiState::Idle);
+
+                // Notice what's about, or whatever was heard
+                let idle = state == AiState::Idle;
+                let noticed = |(.., distance): &(Entity, Point, f32)| {
+                    !idle || *distance <= IDLE_NOTICE_RANGE as f32
+                };
+                let quarry = quarry.filter(noticed);
+                let threat = threat.filter(noticed);
+                match (state, quarry) {
+                    (AiState::Flee { .. }, _) => {}
+                    _ if threat.is_some() => {
+                        state = AiState::Flee {
+                            turns: FALL_BACK_TURNS,
+                        }
+                    }
+                    (_, Some((_, there, _))) => {
+                        state = AiState::Hunt {
+                            x: there.x,
+                            y: there.y,
+                        }
+                    }
+                    (AiState::Hunt { x, y }, None) => state = AiState::Search { x, y },
+                    _ => {
+                        if let Some(alert) = alerted.get(entity) {
+                            state = AiState::Search {
+                                x: alert.x,
+                                y: alert.y,
+                            };
+                        }
+                    }
+                }
+                alerted.remove(entity);
+
+                // The first time it's badly hurt, its nerve is tested
+                let bloodied = combat_stats
+                    .get(entity)
+                    .is_some_and(|stats| stats.hp * 2 <= stats.max_hp);
+                if let (true, Some(morale)) = (bloodied, morale.get_mut(entity)) {
+                    if !morale.tested {
+                        morale.tested = true;
+                        let outcome =
+                            if MonsterTags::has(&monster_tags, entity, MonsterTag::Cautious) {
+                                Outcome::Miss
+                            } else {
+                                roll_2d6(&mut rng, morale.morale).1
+                            };
+                        let (turns, news) = match outcome {
+                            Outcome::Success => (0, "stands its ground"),
+                            Outcome::Partial => (FALL_BACK_TURNS, "falls back"),
+                            Outcome::Miss => (ROUT_TURNS, "breaks and runs"),
+                        };
+                        if turns > 0 {
+                            state = AiState::Flee { turns };
+                        }
+                        if let (true, Some(name)) = (
+                            map.visible_tiles[map.xy_idx(pos.x, pos.y)],
+                            names.get(entity),
+                        ) {
+                            log.entries.push(format!("The {} {}.", name.name, news));
+                        }
+                    }
+                }
+
+                let my_idx = map.xy_idx(pos.x, pos.y);
+                let seen = map.visible_tiles[my_idx];
+                let name = names.get(entity).map_or("", |n| n.name.as_str());
+                let mut released = false;
+                if let (AiState::Hunt { .. }, Some((_, there, distance))) = (state, quarry) {
+                    if let Some(spell) = readied_spell {
+                        if ranged
+                            .get(spell)
+                            .is_some_and(|r| distance <= r.range as f32)
+                        {
+                            wants_use
+                                .insert(
+                                    entity,
+                                    WantsToUseItem {
+                                        item: spell,
+                                        target: Some(there),
+                                    },
+                                )
+                                .expect("Unable to insert spell");
+                            if let Some(known) = known_spells.get_mut(spell) {
+                                known.ready_in = known.cooldown;
+                            }
+                            released = true;
+                        }
+                    } else if aimed
+                        && natural_ranged
+                            .get(entity)
+                            .is_some_and(|attack| distance <= attack.range as f32)
+                    {
+                        wants_shoot
+                            .insert(entity, WantsToShoot { target: there })
+                            .expect("Unable to insert shot");
+                        released = true;
+                    }
+                }
+                if (aimed || readied_spell.is_some()) && !released && seen {
+                    log.entries
+                        .push(format!("The {} lets its chance go by.", name));
+                }
+
+                let mut step: Option<usize> = None;
+                match state {
+                    AiState::Idle => {}
+                    AiState::Wander => {
+                        let exits = map.get_available_exits(my_idx);
+                        if !exits.is_empty() {
+                            let (exit, _cost) =
+                                exits[(rng.roll_dice(1, exits.len() as i32) - 1) as usize];
+                            step = Some(exit);
+                        }
+                    }
+                    AiState::Hunt { .. } if released => {}
+                    AiState::Hunt { x, y } => {
+                        // What it has ready that would reach its quarry
+                        let reach = quarry.map_or(0.0, |(.., distance)| distance);
+                        let spell = (&entities, &known_spells, &ranged)
+                            .join()
+                            .find(|(_, known, range)| {
+                                known.caster == entity
+                                    && known.ready_in == 0
+                                    && reach <= range.range as f32
+                            })
+                            .map(|(spell, ..)| spell);
+                        let shot = natural_ranged
+                            .get(entity)
+                            .is_some_and(|attack| reach <= attack.range as f32);
+                        // How far its longest shot or spell reaches, if it has either
+                        let longest = (&known_spells, &ranged)
+                            .join()
+                            .filter(|(known, _)| known.caster == entity)
+                            .map(|(_, range)| range.range)
+                            .chain(natural_ranged.get(entity).map(|attack| attack.range))
+                            .max()
+                            .unwrap_or(0) as f32;
+                        let from_afar = longest > 0.0;
+                        let backing_off = match quarry {
+                            Some((target, there, distance))
+                                if from_afar && (1.5..KEEP_DISTANCE).contains(&distance) =>
+                            {
+                                retreat(
+                                    &map,
+                                    &dijkstra_maps,
+                                    my_idx,
+                                    Some((target, there)),
+                                    *player_entity,
+                                )
+                            }
+                            _ => None,
+                        };
+                        match quarry {
+                            Some((target, _, distance)) if distance < 1.5 => {
+                                wants_to_melee
+                                    .insert(entity, WantsToMelee { target })
+                                    .expect("Unable to insert attack");
+                            }
+                            Some(_) if backing_off.is_some() => step = backing_off,
+                            Some((target, ..)) if spell.is_some() || shot => {
+                                // Fair warning of what's coming
+                                let doing = match spell {
+                                    Some(spell) => {
+                                        casting
+                                            .insert(entity, Casting { spell })
+                                            .expect("Unable to insert casting");
+                                        let spell_name =
+                                            names.get(spell).map_or("", |n| n.name.as_str());
+                                        format!("starts to cast {} at", spell_name)
+                                    }
+                                    None => {
+                                        taking_aim
+                                            .insert(entity, TakingAim {})
+                                            .expect("Unable to insert aim");
+                                        "takes aim at".to_string()
+                                    }
+                                };
+                                // The party always hears of it, even when it's out of sight
+                                let target_name = names.get(target).map_or("", |n| n.name.as_str());
+                                if target == *player_entity {
+                                    log.entries.push(format!("The {} {} you!", name, doing));
+                                } else if members.get(target).is_some() {
+                                    log.entries
+                                        .push(format!("The {} {} {}!", name, doing, target_name));
+                                } else if seen {
+                                    log.entries.push(format!(
+                                        "The {} {} the {}.",
+                                        name, doing, target_name
+                                    ));
+                                }
+                            }
+                            // Nothing ready yet, so it holds its ground at a distance
+                            Some((.., distance))
+                                if from_afar && (KEEP_DISTANCE..=longest).contains(&distance) => {}
+                            Some((target, ..)) if target == *player_entity => {
+                                step = DijkstraMaps::downhill(&map, &dijkstra_maps.approach, my_idx)
+                            }
+                            _ => {
+                                let hunt_idx = map.xy_idx(x, y);
+                                step = first_step(&mut map, my_idx, hunt_idx);
+                            }
+                        }
+                    }
+                    AiState::Search { x, y } => {
+                        let search_idx = map.xy_idx(x, y);
+                        let beside =
+                            rltk::DistanceAlg::Pythagoras.distance2d(here, Point::new(x, y)) < 1.5;
+                        if search_idx == my_idx || (beside && map.blocked[search_idx]) {
+                            // Nothing here after all
+                            state = AiState::Wander;
+                        } else {
+                            step = first_step(&mut map, my_idx, search_idx);
+                            if step.is_none() {
+                                // There's no getting there, so give up
+                                state = AiState::Wander;
+                            }
+                        }
+                    }
+                    AiState::Flee { turns } => {
+                        state = match quarry {
+                            _ if turns > 1 => AiState::Flee { turns: turns - 1 },
+                            Some((_, there, _)) => AiState::Hunt {
+                                x: there.x,
+                                y: there.y,
+                            },
+                            None => AiState::Wander,
+                        };
+                        // Back away while it can, from whatever it fears or else whatever it
+                        // was fighting
+                        let danger = threat.or(quarry);
+                        step = retreat(
+                            &map,
+                            &dijkstra_maps,
+                            my_idx,
+                            danger.map(|(from, there, _)| (from, there)),
+                            *player_entity,
+                        );
+                        if let (None, Some((target, _, distance))) = (step, danger) {
+                            if distance < 1.5 {
+                                // Cornered, so it fights on
+                                wants_to_melee
+                                    .insert(entity, WantsToMelee { target })
+                                    .expect("Unable to insert attack");
+                            }
+                        }
+                    }
+                }
+
+                if let Some(step) = step {
+                    if map.closed_doors.contains(&step) {
+                        // The way is shut; open the door rather than stepping into it
+                        let door_idx = step;
+                        let door = map.tile_content[door_idx]
+                            .iter()
+                            .find(|e| doors.get(**e).is_some())
+                            .copied();
+                        if let Some(door) = door {
+                            Door::open_door(
+                                door,
+                                &mut doors,
+                                &mut blocks_movement,
+                                &mut blocks_visibility,
+                                &mut renderables,
+                            );
+                            map.blocked[door_idx] = false;
+                            map.view_blocked.remove(&door_idx);
+                            map.closed_doors.remove(&door_idx);
+                            MakesNoise::make(&mut noises, door, DOOR_NOISE);
+                            doors_opened = true;
+                        }
+                    } else if !map.blocked[step] {
+                        map.blocked[my_idx] = false;
+                        pos.x = step as i32 % map.width;
+                        pos.y = step as i32 / map.width;
+                        map.blocked[step] = true;
                         viewshed.dirty = true;
                     }
                 }
+
+                ai_states
+                    .insert(entity, state)
+                    .expect("Unable to insert AI state");
             }
         }
+
+        // An opened door changes what everyone can see
+        if doors_opened {
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
+
+/// The first step on the way from `from` to `to`, if there is a way. Whoever is standing at `to`
+/// isn't in the way.
+///
+/// The shared `DijkstraMaps` only lead to the player, so this is for everywhere else: a monster of
+/// another faction, one of the party not under control, or a spot it heard something. Each of
+/// those goals belongs to a single monster, so one A* search is cheaper than a whole map for it.
+fn first_step(map: &mut Map, from: usize, to: usize) -> Option<usize> {
+    let blocked = map.blocked[to];
+    map.blocked[to] = false;
+    let path = rltk::a_star_search(from, to, &*map);
+    map.blocked[to] = blocked;
+    if path.success && path.steps.len() > 1 {
+        Some(path.steps[1])
+    } else {
+        None
     }
 }
+
+/// Where to step from `from` to get away from `danger`: down the shared flee map if that's the
+/// player (or nobody in particular), or else straight away from it.
+fn retreat(
+    map: &Map,
+    maps: &DijkstraMaps,
+    from: usize,
+    danger: Option<(Entity, Point)>,
+    player: Entity,
+) -> Option<usize> {
+    match danger {
+        Some((other, there)) if other != player => step_away(map, from, there),
+        _ => DijkstraMaps::downhill(map, &maps.flee, from),
+    }
+}
+
+/// The free tile next to `from` that gets furthest from `danger`, if any gets further at all.
+fn step_away(map: &Map, from: usize, danger: Point) -> Option<usize> {
+    let distance = |idx: usize| {
+        let w = map.width as usize;
+        rltk::DistanceAlg::Pythagoras.distance2d(Point::new(idx % w, idx / w), danger)
+    };
+    map.get_available_exits(from)
+        .iter()
+        .map(|(exit, _cost)| (*exit, distance(*exit)))
+        .filter(|(_, further)| *further > distance(from))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(exit, _)| exit)
+}
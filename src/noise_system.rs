@@ -0,0 +1,138 @@
+use super::{Alerted, MakesNoise, Map, Monster, Position};
+use rltk::{BaseMap, DijkstraMap};
+use specs::prelude::*;
+
+/// How far a footstep carries.
+pub const STEP_NOISE: i32 = 4;
+/// How far a careful footstep carries, when it isn't silent altogether.
+pub const SNEAK_NOISE: i32 = 2;
+/// How far the creak of a door opening carries.
+pub const DOOR_NOISE: i32 = 8;
+/// How far the clash of a fight carries.
+pub const COMBAT_NOISE: i32 = 10;
+
+/// The map as sound travels through it: the same ways a creature could walk, except that a
+/// closed door stops the sound where a creature would just open it.
+struct Earshot<'a>(&'a Map);
+
+impl<'a> BaseMap for Earshot<'a> {
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.0.get_pathing_distance(idx1, idx2)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.0
+            .get_available_exits(idx)
+            .into_iter()
+            .filter(|(exit, _)| !self.0.closed_doors.contains(exit))
+            .collect()
+    }
+}
+
+/// Spreads this turn's noises through the map, flooding out from each along the open ways
+/// (so walls and closed doors stop sound, and it has to find its way around corners). Any
+/// monster the noise reaches goes to see what it was.
+pub struct NoiseSystem {}
+
+impl<'a> System<'a> for NoiseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, MakesNoise>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, Alerted>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, map, mut noises, positions, monsters, mut alerted) = data;
+
+        for (source, noise, source_pos) in (&entities, &noises, &positions).join() {
+            let origin = map.xy_idx(source_pos.x, source_pos.y);
+            let flood = DijkstraMap::new(
+                map.width,
+                map.height,
+                &[origin],
+                &Earshot(&map),
+                noise.volume as f32,
+            );
+            // Creatures block the way, so listen from the tiles around them too
+            let heard_at = |x: i32, y: i32| -> f32 {
+                let mut nearest = f32::MAX;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let (nx, ny) = (x + dx, y + dy);
+                        if nx < 0 || nx >= map.width || ny < 0 || ny >= map.height {
+                            continue;
+                        }
+                        let idx = map.xy_idx(nx, ny);
+                        let distance = if idx == origin { 0.0 } else { flood.map[idx] };
+                        let step = if dx == 0 && dy == 0 { 0.0 } else { 1.0 };
+                        nearest = f32::min(nearest, distance + step);
+                    }
+                }
+                nearest
+            };
+
+            for (listener, _monster, pos) in (&entities, &monsters, &positions).join() {
+                if listener != source && heard_at(pos.x, pos.y) <= noise.volume as f32 {
+                    alerted
+                        .insert(
+                            listener,
+                            Alerted {
+                                x: source_pos.x,
+                                y: source_pos.y,
+                            },
+                        )
+                        .expect("Unable to insert alert");
+                }
+            }
+        }
+
+        noises.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TileType;
+    use super::*;
+
+    /// Shouts down a corridor at a monster, through a doorway that's open or closed.
+    fn heard_through(closed: bool) -> bool {
+        let mut world = World::new();
+        System::setup(&mut NoiseSystem {}, &mut world);
+        let mut map = Map::new(1, 30, 20);
+        for x in 5..25 {
+            let idx = map.xy_idx(x, 10);
+            map.tiles[idx] = TileType::Floor;
+        }
+        map.populate_blocked();
+        if closed {
+            let idx = map.xy_idx(12, 10);
+            map.blocked[idx] = true;
+            map.closed_doors.insert(idx);
+        }
+        world.insert(map);
+        let listener = world
+            .create_entity()
+            .with(Monster {})
+            .with(Position { x: 15, y: 10 })
+            .build();
+        let shouter = world
+            .create_entity()
+            .with(Position { x: 10, y: 10 })
+            .build();
+        MakesNoise::make(&mut world.write_storage(), shouter, COMBAT_NOISE);
+        NoiseSystem {}.run_now(&world);
+        let heard = world.read_storage::<Alerted>().contains(listener);
+        heard
+    }
+
+    #[test]
+    fn noise_passes_open_doors_but_not_closed_ones() {
+        assert!(heard_through(false));
+        assert!(!heard_through(true));
+    }
+}
@@ -0,0 +1,327 @@
+use super::{
+    character::{rest, Ability},
+    dijkstra_maps::DijkstraMaps,
+    gamelog::GameLog,
+    noise_system::{SNEAK_NOISE, STEP_NOISE},
+    Abilities, BlocksTile, CombatStats, Debilities, Energy, Equipped, InBackpack, Initiative,
+    MakesNoise, Map, Monster, Name, PartyMember, Player, Position, RunState, Sneaking,
+    StatusEffects, TileType, Travelling, Viewshed, ACTION_COST,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+/// Keeps the rest of the party at the heels of whoever the player is controlling, while there's
+/// no fight on. Followers walk downhill on the approach map, in marching order. They don't spring
+/// traps: `TrapSystem` only catches whoever is under control. One that has nowhere to go, and
+/// nothing in sight, rests.
+pub struct PartySystem {}
+
+impl<'a> System<'a> for PartySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, DijkstraMaps>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, PartyMember>,
+        ReadStorage<'a, Initiative>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, StatusEffects>,
+        ReadStorage<'a, Sneaking>,
+        WriteStorage<'a, MakesNoise>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, Debilities>,
+        ReadStorage<'a, Abilities>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            leader,
+            runstate,
+            mut map,
+            maps,
+            mut rng,
+            mut log,
+            members,
+            initiative,
+            mut energy,
+            mut positions,
+            mut viewsheds,
+            status_effects,
+            sneaking,
+            mut noises,
+            mut combat_stats,
+            mut debilities,
+            abilities,
+            monsters,
+            names,
+        ) = data;
+
+        if *runstate != RunState::MonsterTurn || maps.approach.is_empty() {
+            return;
+        }
+
+        let mut followers: Vec<(Entity, usize)> = (&entities, &members, !&initiative)
+            .join()
+            .filter(|(entity, ..)| *entity != *leader && Energy::ready(&energy, *entity))
+            .map(|(entity, member, _)| (entity, member.slot))
+            .collect();
+        followers.sort_by_key(|(_, slot)| *slot);
+
+        let volume = if sneaking.get(*leader).is_some() {
+            SNEAK_NOISE
+        } else {
+            STEP_NOISE
+        };
+        for (follower, _) in followers {
+            Energy::spend(&mut energy, follower, ACTION_COST);
+            if status_effects
+                .get(follower)
+                .is_some_and(|statuses| statuses.helpless())
+                || combat_stats.get(follower).is_none_or(|stats| stats.hp < 1)
+            {
+                continue;
+            }
+            let my_idx = match positions.get(follower) {
+                Some(pos) => map.xy_idx(pos.x, pos.y),
+                None => continue,
+            };
+
+            // Close enough to touch the leader is close enough
+            let step = if maps.approach[my_idx] > 1.5 {
+                DijkstraMaps::downhill(&map, &maps.approach, my_idx)
+                    .filter(|step| !map.blocked[*step])
+            } else {
+                None
+            };
+            if let Some(step) = step {
+                map.blocked[my_idx] = false;
+                map.blocked[step] = true;
+                if let Some(pos) = positions.get_mut(follower) {
+                    pos.x = step as i32 % map.width;
+                    pos.y = step as i32 / map.width;
+                }
+                if let Some(viewshed) = viewsheds.get_mut(follower) {
+                    viewshed.dirty = true;
+                }
+                MakesNoise::make(&mut noises, follower, volume);
+                continue;
+            }
+
+            let quiet = viewsheds.get(follower).is_some_and(|viewshed| {
+                !viewshed.visible_tiles.iter().any(|tile| {
+                    map.tile_content[map.xy_idx(tile.x, tile.y)]
+                        .iter()
+                        .any(|e| monsters.get(*e).is_some())
+                })
+            });
+            if let (true, Some(stats)) = (quiet, combat_stats.get_mut(follower)) {
+                let devoted = Abilities::has(&abilities, follower, Ability::Devoted);
+                let recovered = rest(stats, debilities.get_mut(follower), devoted, &mut rng);
+                if let (Some(debility), Some(name)) = (recovered, names.get(follower)) {
+                    log.entries.push(format!(
+                        "{} feels rested, and is no longer {}.",
+                        name.name,
+                        debility.name()
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Puts `member` under the player's control: they become the `Player`, and the camera, the
+/// approach map and the monsters' idea of where the player is all follow them.
+pub fn take_control(ecs: &mut World, member: Entity) {
+    {
+        let mut players = ecs.write_storage::<Player>();
+        players.clear();
+        players
+            .insert(member, Player {})
+            .expect("Unable to insert player");
+        ecs.write_storage::<Travelling>().clear();
+        if let Some(pos) = ecs.read_storage::<Position>().get(member) {
+            *ecs.write_resource::<Point>() = Point::new(pos.x, pos.y);
+        }
+    }
+    ecs.insert(member);
+}
+
+/// In an encounter the party act one at a time, in their places in the turn order. Hands control
+/// to whichever of them goes next: at the start of a round the first of them, otherwise the next
+/// after whoever just went that still has the energy to act. Out of an encounter, nothing changes.
+pub fn pass_control(ecs: &mut World, new_round: bool) {
+    let next = {
+        let player_entity = *ecs.fetch::<Entity>();
+        let initiative = ecs.read_storage::<Initiative>();
+        if initiative.get(player_entity).is_none() {
+            return;
+        }
+        let members = ecs.read_storage::<PartyMember>();
+        let energy = ecs.read_storage::<Energy>();
+        let order = Initiative::turn_order(&ecs.entities(), &initiative, player_entity);
+        let start = if new_round {
+            0
+        } else {
+            order
+                .iter()
+                .position(|e| *e == player_entity)
+                .map_or(0, |i| i + 1)
+        };
+        order[start..]
+            .iter()
+            .chain(order[..start].iter())
+            .find(|e| {
+                members.get(**e).is_some()
+                    && Energy::ready(&energy, **e)
+                    && (new_round || **e != player_entity)
+            })
+            .copied()
+    };
+    if let Some(next) = next {
+        take_control(ecs, next);
+    }
+}
+
+/// Brings the rest of the party along to the leader's side as they arrive on a level together,
+/// filling the nearest open tiles in marching order.
+pub fn gather(ecs: &mut World) {
+    let leader = *ecs.fetch::<Entity>();
+    let start = *ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let entities = ecs.entities();
+    let members = ecs.read_storage::<PartyMember>();
+    let blockers = ecs.read_storage::<BlocksTile>();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+
+    let mut followers: Vec<(Entity, usize)> = (&entities, &members)
+        .join()
+        .filter(|(entity, _)| *entity != leader)
+        .map(|(entity, member)| (entity, member.slot))
+        .collect();
+    followers.sort_by_key(|(_, slot)| *slot);
+
+    // Spread out from the leader over open floor, going around anything in the way
+    let start_idx = map.xy_idx(start.x, start.y);
+    let taken: HashSet<usize> = (&positions, &blockers, !&members)
+        .join()
+        .map(|(pos, ..)| map.xy_idx(pos.x, pos.y))
+        .collect();
+    let mut seen: HashSet<usize> = HashSet::new();
+    seen.insert(start_idx);
+    let mut open = VecDeque::from(vec![start_idx]);
+    let mut free: Vec<usize> = Vec::new();
+    while free.len() < followers.len() {
+        let idx = match open.pop_front() {
+            Some(idx) => idx,
+            None => break,
+        };
+        let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+        for (dx, dy) in [
+            (-1, 0),
+            (1, 0),
+            (0, -1),
+            (0, 1),
+            (-1, -1),
+            (1, -1),
+            (-1, 1),
+            (1, 1),
+        ] {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 1 || nx > map.width - 1 || ny < 1 || ny > map.height - 1 {
+                continue;
+            }
+            let next = map.xy_idx(nx, ny);
+            if map.tiles[next] == TileType::Wall || taken.contains(&next) || !seen.insert(next) {
+                continue;
+            }
+            free.push(next);
+            open.push_back(next);
+        }
+    }
+
+    // Should there ever be no room, the leader's own tile has to do
+    for (i, (follower, _)) in followers.iter().enumerate() {
+        let idx = free.get(i).copied().unwrap_or(start_idx);
+        positions
+            .insert(
+                *follower,
+                Position {
+                    x: idx as i32 % map.width,
+                    y: idx as i32 / map.width,
+                },
+            )
+            .expect("Unable to place party member");
+        if let Some(viewshed) = viewsheds.get_mut(*follower) {
+            viewshed.dirty = true;
+        }
+    }
+}
+
+/// Whoever is under control has died for good. What they carried falls where they did, and the
+/// next of the party (the living first) takes over. Returns false if there's nobody left to.
+pub fn fall(ecs: &mut World) -> bool {
+    let fallen = *ecs.fetch::<Entity>();
+    {
+        let entities = ecs.entities();
+        let mut positions = ecs.write_storage::<Position>();
+        let mut backpack = ecs.write_storage::<InBackpack>();
+        let mut equipped = ecs.write_storage::<Equipped>();
+        let at = positions.get(fallen).cloned();
+        let belongings: Vec<Entity> = entities
+            .join()
+            .filter(|item| {
+                backpack.get(*item).is_some_and(|pack| pack.owner == fallen)
+                    || equipped.get(*item).is_some_and(|e| e.owner == fallen)
+            })
+            .collect();
+        for item in belongings {
+            backpack.remove(item);
+            equipped.remove(item);
+            match &at {
+                Some(at) => {
+                    positions
+                        .insert(item, at.clone())
+                        .expect("Unable to drop item");
+                }
+                None => entities.delete(item).expect("Delete failed"),
+            }
+        }
+
+        if let Some(name) = ecs.read_storage::<Name>().get(fallen) {
+            ecs.write_resource::<GameLog>()
+                .entries
+                .push(format!("{} is dead.", name.name));
+        }
+    }
+    ecs.delete_entity(fallen).expect("Unable to delete");
+
+    let next = {
+        let members = ecs.read_storage::<PartyMember>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        (&ecs.entities(), &members)
+            .join()
+            .min_by_key(|(entity, member)| {
+                let down = combat_stats.get(*entity).is_none_or(|stats| stats.hp < 1);
+                (down, member.slot)
+            })
+            .map(|(entity, _)| entity)
+    };
+    match next {
+        Some(next) => {
+            take_control(ecs, next);
+            true
+        }
+        None => false,
+    }
+}
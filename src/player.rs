@@ -1,19 +1,76 @@
 use super::{
-    gamelog::GameLog, CombatStats, Item, Map, Monster, Player, Position, RunState, State, TileType,
-    Viewshed, WantsToMelee, WantsToPickupItem,
+    character::{rest, Ability, Skill, DEX},
+    dijkstra_maps::DijkstraMaps,
+    gamelog::GameLog,
+    noise_system::{DOOR_NOISE, SNEAK_NOISE, STEP_NOISE},
+    party_system,
+    roll::{roll_2d6, Outcome},
+    Abilities, Ammo, Armor, BlocksTile, BlocksVisibility, CombatStats, Debilities, Destination,
+    Door, EntityMoved, Equipped, Experience, Guarding, Hidden, InBackpack, Initiative, Item, Key,
+    Locked, MakesNoise, Map, Monster, Name, PartyMember, Player, Position, Renderable, RunState,
+    Skills, Sneaking, State, Status, StatusEffects, TileType, Trap, Travelling, Viewshed,
+    WantsToDelay, WantsToMelee, WantsToPickupItem, WantsToSearch, Weapon,
 };
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{Point, RandomNumberGenerator, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
 pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+    let (delta_x, delta_y) = stumble(delta_x, delta_y, ecs);
+    if let Some(obstacle) = move_player(delta_x, delta_y, ecs) {
+        let is_trap = ecs.read_storage::<Trap>().get(obstacle).is_some();
+        if is_trap {
+            try_disarm_trap(obstacle, ecs);
+        } else {
+            try_open_door(obstacle, ecs);
+        }
+    }
+}
+
+/// A confused player goes the wrong way half the time.
+fn stumble(delta_x: i32, delta_y: i32, ecs: &mut World) -> (i32, i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    let confused = status_effects
+        .get(*player_entity)
+        .is_some_and(|statuses| statuses.has(Status::Confused));
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    if !confused || rng.roll_dice(1, 2) == 1 {
+        return (delta_x, delta_y);
+    }
+
+    let mut direction = (0, 0);
+    while direction == (0, 0) {
+        direction = (rng.roll_dice(1, 3) - 2, rng.roll_dice(1, 3) - 2);
+    }
+    if direction != (delta_x, delta_y) {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("You stumble about in confusion.".to_string());
+    }
+    direction
+}
+
+/// Moves or attacks, trading places with anyone in the party who's in the way; if the way is
+/// shut by a closed door, or the player would walk onto a trap they know about, returns that
+/// instead.
+fn move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> Option<Entity> {
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let entities = ecs.entities();
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let doors = ecs.read_storage::<Door>();
+    let traps = ecs.read_storage::<Trap>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let monsters = ecs.read_storage::<Monster>();
+    let weapons = ecs.read_storage::<Weapon>();
+    let equipped = ecs.read_storage::<Equipped>();
     let map = ecs.fetch::<Map>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
+    let members = ecs.read_storage::<PartyMember>();
+    let mut swapped: Option<(Entity, Position)> = None;
 
     for (entity, _player, pos, viewshed) in
         (&entities, &players, &mut positions, &mut viewsheds).join()
@@ -23,11 +80,18 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return None;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
+        let companion = map.tile_content[destination_idx]
+            .iter()
+            .find(|e| members.get(**e).is_some())
+            .copied();
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            if Some(*potential_target) == companion {
+                continue;
+            }
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee
@@ -38,20 +102,505 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return;
+                return None;
             }
         }
 
-        if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+        // A reach weapon can strike at something a tile further on, over open ground
+        let reach = Weapon::wielded_by(&weapons, &equipped, entity).map_or(1, |w| w.range());
+        let (far_x, far_y) = (pos.x + delta_x * 2, pos.y + delta_y * 2);
+        if reach >= 2
+            && !map.blocked[destination_idx]
+            && !map.view_blocked.contains(&destination_idx)
+            && far_x >= 0
+            && far_x < map.width
+            && far_y >= 0
+            && far_y < map.height
+        {
+            let far_idx = map.xy_idx(far_x, far_y);
+            if map.visible_tiles[far_idx] {
+                for potential_target in map.tile_content[far_idx].iter() {
+                    if monsters.get(*potential_target).is_some()
+                        && combat_stats.get(*potential_target).is_some()
+                    {
+                        wants_to_melee
+                            .insert(
+                                entity,
+                                WantsToMelee {
+                                    target: *potential_target,
+                                },
+                            )
+                            .expect("Add target failed");
+                        return None;
+                    }
+                }
+            }
+        }
+
+        for potential_door in map.tile_content[destination_idx].iter() {
+            if let Some(door) = doors.get(*potential_door) {
+                if !door.open {
+                    return Some(*potential_door);
+                }
+            }
+        }
+
+        for potential_trap in map.tile_content[destination_idx].iter() {
+            if traps.get(*potential_trap).is_some() && hidden.get(*potential_trap).is_none() {
+                return Some(*potential_trap);
+            }
+        }
+
+        if !map.blocked[destination_idx] || companion.is_some() {
+            if let Some(companion) = companion {
+                swapped = Some((companion, pos.clone()));
+            }
+            pos.x += delta_x;
+            pos.y += delta_y;
 
             viewshed.dirty = true;
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
+            footsteps(entity, ecs);
+        }
+    }
+
+    if let Some((companion, to)) = swapped {
+        positions.insert(companion, to).expect("Unable to move");
+        if let Some(viewshed) = viewsheds.get_mut(companion) {
+            viewshed.dirty = true;
+        }
+    }
+    None
+}
+
+/// Makes the noise of `entity` taking a step. Sneaking takes a Stealth roll, 2d6+DEX: 10+ makes
+/// no sound at all, 7-9 only a little and 6- as much as walking.
+fn footsteps(entity: Entity, ecs: &World) {
+    let mut volume = STEP_NOISE;
+    if ecs.read_storage::<Sneaking>().get(entity).is_some() {
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let armor = ecs.read_storage::<Armor>();
+        let equipped = ecs.read_storage::<Equipped>();
+        let debilities = ecs.read_storage::<Debilities>();
+        let skills = ecs.read_storage::<Skills>();
+        let dex = combat_stats.get(entity).map_or(0, |stats| stats.dex)
+            + Armor::heavy_penalty(&armor, &equipped, entity)
+            + Debilities::penalty(&debilities, entity, DEX)
+            + Skills::bonus(&skills, entity, Skill::Stealth);
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        volume = match roll_2d6(&mut rng, dex).1 {
+            Outcome::Success => return,
+            Outcome::Partial => SNEAK_NOISE,
+            Outcome::Miss => STEP_NOISE,
+        };
+    }
+    MakesNoise::make(&mut ecs.write_storage::<MakesNoise>(), entity, volume);
+}
+
+/// Bumping a closed door opens it. Locked doors need a key from the backpack (which is used
+/// up) or a lockpicking roll: 2d6+DEX, where 10+ opens the door, 7-9 only gets the lock undone
+/// and 6- gets nowhere.
+fn try_open_door(door: Entity, ecs: &mut World) {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let mut doors = ecs.write_storage::<Door>();
+    let mut locked = ecs.write_storage::<Locked>();
+    let mut blocks_movement = ecs.write_storage::<BlocksTile>();
+    let mut blocks_visibility = ecs.write_storage::<BlocksVisibility>();
+    let mut renderables = ecs.write_storage::<Renderable>();
+    let mut names = ecs.write_storage::<Name>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let keys = ecs.read_storage::<Key>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let armor = ecs.read_storage::<Armor>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let abilities = ecs.read_storage::<Abilities>();
+    let debilities = ecs.read_storage::<Debilities>();
+    let mut experience = ecs.write_storage::<Experience>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    if locked.get(door).is_some() {
+        let key = (&entities, &keys, &backpack)
+            .join()
+            .find(|(_, _, pack)| pack.owner == *player_entity)
+            .map(|(key, _, _)| key);
+        let swings_open = if let Some(key) = key {
+            gamelog
+                .entries
+                .push("You unlock the door, and the key snaps in the lock.".to_string());
+            entities.delete(key).expect("Delete failed");
+            true
+        } else {
+            let dex = combat_stats
+                .get(*player_entity)
+                .map_or(0, |stats| stats.dex)
+                + Armor::heavy_penalty(&armor, &equipped, *player_entity)
+                + Debilities::penalty(&debilities, *player_entity, DEX);
+            let tinker = if Abilities::has(&abilities, *player_entity, Ability::Tinker) {
+                1
+            } else {
+                0
+            };
+            match roll_2d6(&mut rng, dex + tinker).1 {
+                Outcome::Success => {
+                    gamelog.entries.push("You pick the lock.".to_string());
+                    true
+                }
+                Outcome::Partial => {
+                    gamelog.entries.push(
+                        "You get the lock open, but it takes all your attention.".to_string(),
+                    );
+                    false
+                }
+                Outcome::Miss => {
+                    gamelog
+                        .entries
+                        .push("The door is locked, and the lock won't budge.".to_string());
+                    Experience::mark(&mut experience, *player_entity, 1);
+                    return;
+                }
+            }
+        };
+
+        locked.remove(door);
+        names
+            .insert(
+                door,
+                Name {
+                    name: "Door".to_string(),
+                },
+            )
+            .expect("Unable to rename door");
+        if !swings_open {
+            return;
+        }
+    }
+
+    Door::open_door(
+        door,
+        &mut doors,
+        &mut blocks_movement,
+        &mut blocks_visibility,
+        &mut renderables,
+    );
+    MakesNoise::make(&mut ecs.write_storage::<MakesNoise>(), door, DOOR_NOISE);
+    if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+        viewshed.dirty = true;
+    }
+    gamelog.entries.push("You open the door.".to_string());
+}
+
+/// Bumping a trap the player knows about tries to disarm it, with 2d6+DEX: 10+ takes it apart,
+/// 7-9 gets nowhere, and 6- fumbles it and stumbles onto the trap.
+fn try_disarm_trap(trap: Entity, ecs: &mut World) {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let armor = ecs.read_storage::<Armor>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let abilities = ecs.read_storage::<Abilities>();
+    let debilities = ecs.read_storage::<Debilities>();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut entity_moved = ecs.write_storage::<EntityMoved>();
+    let mut experience = ecs.write_storage::<Experience>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let trap_name = names
+        .get(trap)
+        .map_or("trap".to_string(), |n| n.name.clone());
+    let dex = combat_stats
+        .get(*player_entity)
+        .map_or(0, |stats| stats.dex)
+        + Armor::heavy_penalty(&armor, &equipped, *player_entity)
+        + Debilities::penalty(&debilities, *player_entity, DEX);
+    let tinker = if Abilities::has(&abilities, *player_entity, Ability::Tinker) {
+        1
+    } else {
+        0
+    };
+    match roll_2d6(&mut rng, dex + tinker).1 {
+        Outcome::Success => {
+            gamelog
+                .entries
+                .push(format!("You carefully disarm the {}.", trap_name));
+            entities.delete(trap).expect("Delete failed");
+        }
+        Outcome::Partial => {
+            gamelog.entries.push(format!(
+                "You can't work out how the {} comes apart. It's still armed.",
+                trap_name
+            ));
+        }
+        Outcome::Miss => {
+            gamelog.entries.push(format!(
+                "Your hand slips, and you stumble onto the {}!",
+                trap_name
+            ));
+            Experience::mark(&mut experience, *player_entity, 1);
+            let trap_pos = positions.get(trap).cloned();
+            if let (Some(trap_pos), Some(pos)) = (trap_pos, positions.get_mut(*player_entity)) {
+                pos.x = trap_pos.x;
+                pos.y = trap_pos.y;
+                let mut ppos = ecs.write_resource::<Point>();
+                ppos.x = pos.x;
+                ppos.y = pos.y;
+                entity_moved
+                    .insert(*player_entity, EntityMoved {})
+                    .expect("Unable to insert marker");
+            }
+            if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
+
+/// Picks a target for the readied ranged weapon, as long as there's ammo for it.
+fn fire(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let weapons = ecs.read_storage::<Weapon>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let ammo = ecs.read_storage::<Ammo>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let weapon = match Weapon::readied_by(&weapons, &equipped, *player_entity) {
+        Some(weapon) => weapon,
+        None => {
+            gamelog
+                .entries
+                .push("You have no ranged weapon ready.".to_string());
+            return RunState::AwaitingInput;
+        }
+    };
+    match weapon.ammo {
+        Some(kind)
+            if Ammo::carried_by(&entities, &ammo, &backpack, *player_entity, kind).is_some() =>
+        {
+            RunState::ShowFireTargeting {
+                range: weapon.range(),
+            }
         }
+        Some(kind) => {
+            gamelog
+                .entries
+                .push(format!("You have no {} to shoot.", kind.name()));
+            RunState::AwaitingInput
+        }
+        None => RunState::AwaitingInput,
+    }
+}
+
+/// Lets everyone after the player in this round act first, and takes the player's turn after
+/// them. Only makes sense in an encounter, with someone left to go.
+fn delay(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let initiative = ecs.read_storage::<Initiative>();
+    let order = Initiative::turn_order(&ecs.entities(), &initiative, *player_entity);
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    if order.is_empty() {
+        gamelog
+            .entries
+            .push("There's nothing to wait for.".to_string());
+        return RunState::AwaitingInput;
+    }
+    if order.last() == Some(&*player_entity) {
+        gamelog
+            .entries
+            .push("You are already last to act.".to_string());
+        return RunState::AwaitingInput;
+    }
+
+    let mut wants_delay = ecs.write_storage::<WantsToDelay>();
+    wants_delay
+        .insert(*player_entity, WantsToDelay {})
+        .expect("Unable to insert intent");
+    gamelog
+        .entries
+        .push("You hold back, and let the others go first.".to_string());
+    RunState::MonsterTurn
+}
+
+/// Out of an encounter, hands control to the next of the party in marching order, who leads
+/// from then on. In one, everyone waits for their own turn. Takes no time.
+fn switch_character(ecs: &mut World) -> RunState {
+    let next = {
+        let player_entity = *ecs.fetch::<Entity>();
+        if ecs
+            .read_storage::<Initiative>()
+            .get(player_entity)
+            .is_some()
+        {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog
+                .entries
+                .push("In a fight, everyone waits for their turn.".to_string());
+            return RunState::AwaitingInput;
+        }
+        let members = ecs.read_storage::<PartyMember>();
+        let mut party: Vec<(Entity, usize)> = (&ecs.entities(), &members)
+            .join()
+            .map(|(entity, member)| (entity, member.slot))
+            .collect();
+        party.sort_by_key(|(_, slot)| *slot);
+        if party.len() < 2 {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog
+                .entries
+                .push("There's nobody else to take the lead.".to_string());
+            return RunState::AwaitingInput;
+        }
+        let place = party
+            .iter()
+            .position(|(entity, _)| *entity == player_entity)
+            .unwrap_or(0);
+        party[(place + 1) % party.len()].0
+    };
+    party_system::take_control(ecs, next);
+
+    let names = ecs.read_storage::<Name>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    if let Some(name) = names.get(next) {
+        gamelog
+            .entries
+            .push(format!("{} takes the lead.", name.name));
+    }
+    RunState::AwaitingInput
+}
+
+/// Spends the turn ready to strike back at the first thing that attacks.
+fn guard(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut guarding = ecs.write_storage::<Guarding>();
+    guarding
+        .insert(*player_entity, Guarding {})
+        .expect("Unable to insert guard");
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog.entries.push("You stand on guard.".to_string());
+    RunState::PlayerTurn
+}
+
+/// Sets off for `destination`, a step at a time.
+fn travel(ecs: &mut World, destination: Destination) -> RunState {
+    {
+        let player_entity = ecs.fetch::<Entity>();
+        let mut travelling = ecs.write_storage::<Travelling>();
+        travelling
+            .insert(*player_entity, Travelling { destination })
+            .expect("Unable to insert travel");
+    }
+    keep_travelling(ecs, false).unwrap_or(RunState::AwaitingInput)
+}
+
+/// Takes the next step for a travelling player, following the shared Dijkstra maps. Travel
+/// stops on arrival, on a key press (`interrupted`), when there's no way on, or as soon as a
+/// monster comes into view.
+fn keep_travelling(ecs: &mut World, interrupted: bool) -> Option<RunState> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let destination = ecs
+        .read_storage::<Travelling>()
+        .get(player_entity)?
+        .destination;
+    if interrupted {
+        ecs.write_storage::<Travelling>().remove(player_entity);
+        return Some(RunState::AwaitingInput);
+    }
+
+    let (step, why) = {
+        let map = ecs.fetch::<Map>();
+        let player_pos = ecs.fetch::<Point>();
+        let maps = ecs.fetch::<DijkstraMaps>();
+        let entities = ecs.entities();
+        let monsters = ecs.read_storage::<Monster>();
+        let positions = ecs.read_storage::<Position>();
+        let hidden = ecs.read_storage::<Hidden>();
+        let traps = ecs.read_storage::<Trap>();
+        let names = ecs.read_storage::<Name>();
+
+        let in_view = (&entities, &monsters, &positions, !&hidden)
+            .join()
+            .find(|(_, _, pos, _)| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+            .map(|(entity, ..)| entity);
+        let values = match destination {
+            Destination::Unexplored => &maps.explore,
+            Destination::Stairs => &maps.stairs,
+        };
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+        let step = DijkstraMaps::downhill(&map, values, player_idx);
+        let trapped = step.is_some_and(|step| {
+            map.tile_content[step]
+                .iter()
+                .any(|e| traps.get(*e).is_some() && hidden.get(*e).is_none())
+        });
+
+        let why = if let Some(monster) = in_view {
+            let name = names.get(monster).map_or("something", |n| n.name.as_str());
+            Some(format!("You spot the {}, and stop.", name))
+        } else if destination == Destination::Stairs && values[player_idx] == 0.0 {
+            Some("You reach the stairs.".to_string())
+        } else if trapped {
+            Some("There's a trap in the way.".to_string())
+        } else if step.is_some() {
+            None
+        } else if destination == Destination::Unexplored {
+            Some("There's nowhere left to explore.".to_string())
+        } else {
+            Some("You don't know the way to the stairs.".to_string())
+        };
+        (step, why)
+    };
+
+    if let Some(why) = why {
+        ecs.write_storage::<Travelling>().remove(player_entity);
+        ecs.fetch_mut::<GameLog>().entries.push(why);
+        return Some(RunState::AwaitingInput);
+    }
+
+    let step = step?;
+    let (x, y) = {
+        let map = ecs.fetch::<Map>();
+        (step as i32 % map.width, step as i32 / map.width)
+    };
+    let player_pos = *ecs.fetch::<Point>();
+    try_move_player(x - player_pos.x, y - player_pos.y, ecs);
+    Some(RunState::PlayerTurn)
+}
+
+/// Starts or stops sneaking, which takes no time.
+fn toggle_sneaking(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut sneaking = ecs.write_storage::<Sneaking>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    if sneaking.remove(*player_entity).is_some() {
+        gamelog.entries.push("You stop sneaking.".to_string());
+    } else {
+        sneaking
+            .insert(*player_entity, Sneaking {})
+            .expect("Unable to insert sneaking");
+        gamelog.entries.push("You start sneaking.".to_string());
     }
+    RunState::AwaitingInput
+}
+
+/// Spends the turn looking for traps; `TrapDetectionSystem` makes the roll.
+fn search_for_traps(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut wants_search = ecs.write_storage::<WantsToSearch>();
+    wants_search
+        .insert(*player_entity, WantsToSearch {})
+        .expect("Unable to insert intent");
+    RunState::PlayerTurn
 }
 
 pub fn try_next_level(ecs: &mut World) -> bool {
@@ -69,6 +618,21 @@ pub fn try_next_level(ecs: &mut World) -> bool {
     }
 }
 
+pub fn try_previous_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::UpStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way up from here.".to_string());
+        false
+    }
+}
+
 fn get_item(ecs: &mut World) {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
@@ -126,15 +690,61 @@ fn skip_turn(ecs: &mut World) -> RunState {
     }
 
     if can_heal {
+        let abilities = ecs.read_storage::<Abilities>();
+        let devoted = Abilities::has(&abilities, *player_entity, Ability::Devoted);
         let mut health_components = ecs.write_storage::<CombatStats>();
         let player_hp = health_components.get_mut(*player_entity).unwrap();
-        player_hp.hp = i32::min(player_hp.hp + 1, player_hp.max_hp);
+        let mut debilities = ecs.write_storage::<Debilities>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let recovered = rest(
+            player_hp,
+            debilities.get_mut(*player_entity),
+            devoted,
+            &mut rng,
+        );
+        if let Some(debility) = recovered {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.entries.push(format!(
+                "You feel rested, and are no longer {}.",
+                debility.name()
+            ));
+        }
     }
 
     RunState::PlayerTurn
 }
 
+/// Spends the turn for the player if a status won't let them act, with a note as to why.
+fn lose_turn(ecs: &mut World) -> Option<RunState> {
+    let player_entity = ecs.fetch::<Entity>();
+    let status_effects = ecs.read_storage::<StatusEffects>();
+    let statuses = status_effects.get(*player_entity)?;
+    if !statuses.helpless() {
+        return None;
+    }
+
+    let why = if statuses.has(Status::Sleeping) {
+        "You sleep on."
+    } else {
+        "You are stunned, and can't act."
+    };
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog.entries.push(why.to_string());
+    Some(RunState::PlayerTurn)
+}
+
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    // A guard only lasts until the player's next turn
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    gs.ecs.write_storage::<Guarding>().remove(player_entity);
+
+    if let Some(runstate) = lose_turn(&mut gs.ecs) {
+        return runstate;
+    }
+    if let Some(runstate) = keep_travelling(&mut gs.ecs, ctx.key.is_some()) {
+        return runstate;
+    }
+
     // Player movement
     match ctx.key {
         None => return RunState::AwaitingInput, // Nothing happened
@@ -167,18 +777,43 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             // Skip Turn
             VirtualKeyCode::Numpad5 | VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
 
+            // Search for traps
+            VirtualKeyCode::S => return search_for_traps(&mut gs.ecs),
+
             // Level changes
             VirtualKeyCode::Period => {
                 if try_next_level(&mut gs.ecs) {
                     return RunState::NextLevel;
                 }
             }
+            VirtualKeyCode::Comma => {
+                if try_previous_level(&mut gs.ecs) {
+                    return RunState::PreviousLevel;
+                }
+            }
 
             // Picking up items
             VirtualKeyCode::G => get_item(&mut gs.ecs),
             VirtualKeyCode::I => return RunState::ShowInventory,
             VirtualKeyCode::D => return RunState::ShowDropItem,
             VirtualKeyCode::R => return RunState::ShowRemoveItem,
+            VirtualKeyCode::C => return RunState::ShowCharacter,
+
+            // Shooting
+            VirtualKeyCode::F => return fire(&mut gs.ecs),
+
+            // The party
+            VirtualKeyCode::Tab => return switch_character(&mut gs.ecs),
+
+            // Encounter actions
+            VirtualKeyCode::W => return delay(&mut gs.ecs),
+            VirtualKeyCode::X => return guard(&mut gs.ecs),
+
+            VirtualKeyCode::Z => return toggle_sneaking(&mut gs.ecs),
+
+            // Travel
+            VirtualKeyCode::E => return travel(&mut gs.ecs, Destination::Unexplored),
+            VirtualKeyCode::T => return travel(&mut gs.ecs, Destination::Stairs),
 
             // Save and Quit
             VirtualKeyCode::Escape => return RunState::SaveGame,
@@ -44,7 +44,7 @@ impl RandomTable {
         let mut roll = rng.roll_dice(1, self.total_weight) - 1;
         let mut index: usize = 0;
 
-        while roll > 0 {
+        while index < self.entries.len() {
             if roll < self.entries[index].weight {
                 return self.entries[index].name.clone();
             }
@@ -56,3 +56,50 @@ impl RandomTable {
         "None".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_table_rolls_none() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(RandomTable::new().roll(&mut rng), "None");
+    }
+
+    #[test]
+    fn every_roll_lands_on_an_entry() {
+        // Including the lowest roll, which mustn't fall straight through the table
+        let table = RandomTable::new().add("Goblin", 1).add("Orc", 1);
+        let mut rng = RandomNumberGenerator::seeded(1);
+        for _ in 0..1000 {
+            let name = table.roll(&mut rng);
+            assert!(name == "Goblin" || name == "Orc", "rolled {}", name);
+        }
+    }
+
+    #[test]
+    fn a_single_entry_always_comes_up() {
+        let table = RandomTable::new().add("Goblin", 1);
+        let mut rng = RandomNumberGenerator::seeded(1);
+        for _ in 0..100 {
+            assert_eq!(table.roll(&mut rng), "Goblin");
+        }
+    }
+
+    #[test]
+    fn entries_come_up_by_weight_and_never_at_zero() {
+        let table = RandomTable::new()
+            .add("Goblin", 3)
+            .add("Orc", 1)
+            .add("Dragon", 0)
+            .add("Ogre", -2);
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let goblins = (0..4000)
+            .map(|_| table.roll(&mut rng))
+            .inspect(|name| assert!(name == "Goblin" || name == "Orc", "rolled {}", name))
+            .filter(|name| name == "Goblin")
+            .count();
+        assert!((2700..3300).contains(&goblins), "{} goblins", goblins);
+    }
+}
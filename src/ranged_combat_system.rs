@@ -0,0 +1,420 @@
+use super::{
+    character::DEX,
+    gamelog::GameLog,
+    noise_system::COMBAT_NOISE,
+    roll::{roll_2d6, Outcome},
+    Alerted, Ammo, Armor, CombatStats, Debilities, Equipped, Experience, InBackpack, Item,
+    MakesNoise, Map, Monster, MonsterTag, MonsterTags, Name, NaturalArmor, NaturalRangedAttack,
+    PartyMember, Position, Renderable, SerializeMe, Status, StatusEffects, SufferDamage,
+    WantsToShoot, Weapon,
+};
+use rltk::{BaseMap, LineAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+/// How many turns a shot from a bad position leaves the shooter slowed.
+const BAD_POSITION_TURNS: i32 = 2;
+/// The chance, 1 in this, that a spent arrow or stone breaks instead of landing.
+const AMMO_BREAK_CHANCE: i32 = 2;
+
+/// Resolves shots. The shot flies along a straight line until a wall, a closed door or the first
+/// creature in the way stops it. The player shoots with their readied ranged weapon, and Volley
+/// (2d6+DEX) decides how it goes: 10+ hits, 7-9 hits but costs an extra shot of ammo (or, with
+/// none to spare, leaves the shooter in a bad position) and 6- misses. Each shot spent has an
+/// even chance of landing where the shot stopped, to be picked up again.
+///
+/// Monsters shoot with their own `NaturalRangedAttack` and never run out. Whoever they hit Defies
+/// Danger (2d6+DEX): 10+ gets clear, 7-9 is only grazed for half damage and 6- takes all of it.
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Read<'a, LazyUpdate>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Weapon>,
+        ReadStorage<'a, Equipped>,
+        WriteStorage<'a, Ammo>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Renderable>,
+        // What turns a shot aside
+        (ReadStorage<'a, Armor>, ReadStorage<'a, NaturalArmor>),
+        ReadStorage<'a, Debilities>,
+        ReadStorage<'a, MonsterTags>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, Alerted>,
+        WriteStorage<'a, Experience>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, MakesNoise>,
+        ReadStorage<'a, NaturalRangedAttack>,
+        ReadStorage<'a, PartyMember>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            mut log,
+            mut rng,
+            lazy,
+            mut wants_shoot,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            weapons,
+            equipped,
+            mut ammo,
+            backpack,
+            positions,
+            renderables,
+            (armor, natural_armor),
+            debilities,
+            monster_tags,
+            monsters,
+            mut alerted,
+            mut experience,
+            mut status_effects,
+            mut noises,
+            natural_ranged,
+            members,
+        ) = data;
+
+        // Worn and natural armor only; `Armored` just helps in melee
+        let defence = |target: Entity| -> i32 {
+            Armor::total(&armor, &equipped, target)
+                + natural_armor.get(target).map_or(0, |a| a.armor)
+        };
+
+        for (shooter, wants_shoot, name, stats, pos) in
+            (&entities, &wants_shoot, &names, &combat_stats, &positions).join()
+        {
+            let start = Point::new(pos.x, pos.y);
+
+            if let Some(attack) = natural_ranged.get(shooter) {
+                let (_, struck) = trace_shot(&map, &combat_stats, start, wants_shoot.target);
+                let target = match struck {
+                    Some(target) => target,
+                    None => {
+                        log.entries
+                            .push(format!("{} shoots, but hits nothing.", &name.name));
+                        continue;
+                    }
+                };
+                let target_name = &names.get(target).unwrap().name;
+                // Defy Danger
+                let helpless = status_effects
+                    .get(target)
+                    .is_some_and(|statuses| statuses.helpless());
+                let outcome = match combat_stats.get(target) {
+                    Some(target_stats) if !helpless => {
+                        let dex = target_stats.dex
+                            + Armor::heavy_penalty(&armor, &equipped, target)
+                            + Debilities::penalty(&debilities, target, DEX);
+                        roll_2d6(&mut rng, dex).1
+                    }
+                    _ => Outcome::Miss,
+                };
+                let damage = i32::max(0, rng.roll(attack.damage) - defence(target));
+                let damage = match outcome {
+                    Outcome::Success => {
+                        log.entries.push(format!(
+                            "{} shoots at {}, who gets clear.",
+                            &name.name, target_name
+                        ));
+                        0
+                    }
+                    Outcome::Partial => {
+                        log.entries.push(format!(
+                            "{} shoots at {}, who is only grazed, taking {} hp.",
+                            &name.name,
+                            target_name,
+                            damage / 2
+                        ));
+                        damage / 2
+                    }
+                    Outcome::Miss => {
+                        Experience::mark(&mut experience, target, 1);
+                        log.entries.push(format!(
+                            "{} shoots {}, hitting for {} hp.",
+                            &name.name, target_name, damage
+                        ));
+                        damage
+                    }
+                };
+                SufferDamage::new_damage(&mut inflict_damage, target, damage, None);
+                MakesNoise::make(&mut noises, target, COMBAT_NOISE);
+                continue;
+            }
+
+            let weapon = match Weapon::readied_by(&weapons, &equipped, shooter) {
+                Some(weapon) => weapon,
+                None => continue,
+            };
+            let kind = match weapon.ammo {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let stack = match Ammo::carried_by(&entities, &ammo, &backpack, shooter, kind) {
+                Some(stack) => stack,
+                None => {
+                    log.entries
+                        .push(format!("You have no {} left.", kind.name()));
+                    continue;
+                }
+            };
+
+            let (landing, struck) = trace_shot(&map, &combat_stats, start, wants_shoot.target);
+
+            let mut spent = 1;
+            match struck {
+                None if landing == wants_shoot.target => log
+                    .entries
+                    .push(format!("{} shoots, but hits nothing.", &name.name)),
+                None => log.entries.push(format!(
+                    "{} shoots, but the shot is stopped short.",
+                    &name.name
+                )),
+                Some(target) => {
+                    let target_name = &names.get(target).unwrap().name;
+                    // Volley
+                    let small = if MonsterTags::has(&monster_tags, target, MonsterTag::Small) {
+                        -1
+                    } else {
+                        0
+                    };
+                    let dex = stats.dex
+                        + Armor::heavy_penalty(&armor, &equipped, shooter)
+                        + Debilities::penalty(&debilities, shooter, DEX);
+                    let (_, outcome) = roll_2d6(&mut rng, dex + small);
+                    if outcome == Outcome::Miss {
+                        Experience::mark(&mut experience, shooter, 1);
+                        log.entries.push(format!(
+                            "{} shoots at {}, but misses.",
+                            &name.name, target_name
+                        ));
+                    } else {
+                        let damage = i32::max(
+                            0,
+                            rng.roll(weapon.damage)
+                                - i32::max(0, defence(target) - weapon.piercing()),
+                        );
+                        log.entries.push(format!(
+                            "{} shoots {}, hitting for {} hp.",
+                            &name.name, target_name, damage
+                        ));
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            target,
+                            damage,
+                            members.get(shooter).map(|member| member.slot),
+                        );
+                        MakesNoise::make(&mut noises, target, COMBAT_NOISE);
+                        if monsters.get(target).is_some() {
+                            alerted
+                                .insert(target, Alerted { x: pos.x, y: pos.y })
+                                .expect("Unable to insert alert");
+                        }
+                    }
+                    if outcome == Outcome::Partial {
+                        if ammo.get(stack).map_or(0, |a| a.count) > 1 {
+                            spent += 1;
+                            log.entries.push(format!(
+                                "It takes more than one shot, and costs you extra {}.",
+                                kind.name()
+                            ));
+                        } else {
+                            StatusEffects::add(
+                                &mut status_effects,
+                                shooter,
+                                Status::Slowed,
+                                BAD_POSITION_TURNS,
+                            );
+                            log.entries.push(
+                                "You stretch for a clear shot, and are left off balance."
+                                    .to_string(),
+                            );
+                        }
+                    }
+                }
+            }
+
+            let mut left = 0;
+            if let Some(stack) = ammo.get_mut(stack) {
+                stack.count -= spent;
+                left = stack.count;
+            }
+            if left < 1 {
+                entities.delete(stack).expect("Unable to delete");
+                log.entries
+                    .push(format!("That was the last of your {}.", kind.name()));
+            }
+
+            // Whatever survives the shot lands where it stopped
+            let recovered = (0..spent)
+                .filter(|_| rng.roll_dice(1, AMMO_BREAK_CHANCE) > 1)
+                .count() as i32;
+            if recovered > 0 && landing != start {
+                let idx = map.xy_idx(landing.x, landing.y);
+                let pile = map.tile_content[idx].iter().find(|e| {
+                    backpack.get(**e).is_none() && ammo.get(**e).is_some_and(|a| a.kind == kind)
+                });
+                if let Some(pile) = pile {
+                    if let Some(pile) = ammo.get_mut(*pile) {
+                        pile.count += recovered;
+                    }
+                } else if let (Some(renderable), Some(ammo_name)) =
+                    (renderables.get(stack), names.get(stack))
+                {
+                    lazy.create_entity(&entities)
+                        .with(Position {
+                            x: landing.x,
+                            y: landing.y,
+                        })
+                        .with(renderable.clone())
+                        .with(ammo_name.clone())
+                        .with(Item {})
+                        .with(Ammo {
+                            kind,
+                            count: recovered,
+                        })
+                        .marked::<SimpleMarker<SerializeMe>>()
+                        .build();
+                }
+            }
+        }
+
+        wants_shoot.clear();
+    }
+}
+
+/// Follows a shot from `start` towards `target` until something stops it, returning where it
+/// ended up and who, if anyone, it struck.
+fn trace_shot(
+    map: &Map,
+    combat_stats: &ReadStorage<CombatStats>,
+    start: Point,
+    target: Point,
+) -> (Point, Option<Entity>) {
+    let mut landing = start;
+    for point in rltk::line2d(LineAlg::Bresenham, start, target) {
+        if point == start {
+            continue;
+        }
+        let idx = map.xy_idx(point.x, point.y);
+        if map.is_opaque(idx) {
+            break;
+        }
+        landing = point;
+        let struck = map.tile_content[idx]
+            .iter()
+            .find(|e| combat_stats.get(**e).is_some_and(|s| s.hp > 0))
+            .copied();
+        if struck.is_some() {
+            return (landing, struck);
+        }
+    }
+    (landing, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{character::Ability, Abilities, ArmorKind, EquipmentSlot, TileType};
+    use super::*;
+
+    /// An archer shoots at the player, who can't get clear. Returns the damage the player takes.
+    fn shot(seed: u64, abilities: Vec<Ability>, armor: i32) -> i32 {
+        let mut world = World::new();
+        System::setup(&mut RangedCombatSystem {}, &mut world);
+        world.register::<Abilities>();
+        world.insert(GameLog { entries: vec![] });
+        world.insert(RandomNumberGenerator::seeded(seed));
+        let mut map = Map::new(1, 20, 10);
+        for x in 1..19 {
+            let idx = map.xy_idx(x, 5);
+            map.tiles[idx] = TileType::Floor;
+        }
+        map.populate_blocked();
+
+        let player = world
+            .create_entity()
+            .with(Name {
+                name: "Player".to_string(),
+            })
+            .with(Position { x: 10, y: 5 })
+            .with(CombatStats {
+                str: 0,
+                dex: -12,
+                con: 0,
+                int: 0,
+                wis: 0,
+                cha: 0,
+                max_hp: 100,
+                hp: 100,
+            })
+            .with(Abilities { abilities })
+            .build();
+        world
+            .create_entity()
+            .with(Armor {
+                kind: ArmorKind::Light,
+                points: armor,
+            })
+            .with(Equipped {
+                owner: player,
+                slot: EquipmentSlot::Body,
+            })
+            .build();
+        let idx = map.xy_idx(10, 5);
+        map.tile_content[idx].push(player);
+        world.insert(map);
+        world
+            .create_entity()
+            .with(Name {
+                name: "Archer".to_string(),
+            })
+            .with(Position { x: 4, y: 5 })
+            .with(CombatStats {
+                str: 0,
+                dex: 0,
+                con: 0,
+                int: 0,
+                wis: 0,
+                cha: 0,
+                max_hp: 10,
+                hp: 10,
+            })
+            .with(NaturalRangedAttack {
+                damage: rltk::DiceType::new(1, 6, 6),
+                range: 8,
+            })
+            .with(WantsToShoot {
+                target: Point::new(10, 5),
+            })
+            .build();
+
+        RangedCombatSystem {}.run_now(&world);
+        let hurt = world.read_storage::<SufferDamage>();
+        let taken = hurt
+            .get(player)
+            .map_or(0, |hurt| hurt.amount.iter().map(|(amount, _)| amount).sum());
+        taken
+    }
+
+    #[test]
+    fn armor_turns_shots_aside_but_armored_does_not() {
+        for seed in 0..20 {
+            let full = shot(seed, vec![], 0);
+            assert!(full >= 7, "the archer always hits for d6+6");
+            assert_eq!(shot(seed, vec![Ability::Armored], 0), full);
+            assert_eq!(shot(seed, vec![], 2), full - 2);
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use super::super::Reaction;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// A side creatures can be on, and how it treats the others. `reactions` is keyed by faction
+/// name, with `Default` covering any faction not listed.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Faction {
+    pub name: String,
+    pub reactions: HashMap<String, Reaction>,
+}
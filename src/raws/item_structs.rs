@@ -0,0 +1,68 @@
+use super::super::{AmmoKind, ArmorKind, EquipmentSlot, Status, WeaponTag};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Item {
+    pub name: String,
+    pub renderable: Renderable,
+    #[serde(default)]
+    pub consumable: bool,
+    pub provides_healing: Option<i32>,
+    pub ranged: Option<i32>,
+    pub inflicts_damage: Option<i32>,
+    pub area_of_effect: Option<i32>,
+    pub inflicts_status: Option<InflictsStatus>,
+    #[serde(default)]
+    pub cures_debilities: bool,
+    pub equippable: Option<Equippable>,
+    pub ammo: Option<Ammo>,
+    #[serde(default)]
+    pub key: bool,
+}
+
+/// A stack of ammunition, and how many are in it when found.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Ammo {
+    pub kind: AmmoKind,
+    pub count: i32,
+}
+
+/// A status and how many turns it lasts, for items and mobs that pass one on.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct InflictsStatus {
+    pub status: Status,
+    pub turns: i32,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Renderable {
+    pub glyph: String,
+    pub fg: String,
+    #[serde(default = "default_bg")]
+    pub bg: String,
+    pub order: i32,
+}
+
+fn default_bg() -> String {
+    "#000000".to_string()
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+    pub melee_power_bonus: Option<i32>,
+    pub armor: Option<ArmorKind>,
+    /// Overrides the armor points the kind of armor is usually worth.
+    pub armor_points: Option<i32>,
+    /// A weapon's damage dice, such as "1d8".
+    pub damage: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<WeaponTag>,
+    /// The kind of ammunition a Ranged weapon shoots.
+    pub ammo: Option<AmmoKind>,
+}
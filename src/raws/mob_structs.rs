@@ -0,0 +1,87 @@
+use super::super::{Debility, MonsterTag, NORMAL_SPEED};
+use super::item_structs::{InflictsStatus, Renderable};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Mob {
+    pub name: String,
+    pub renderable: Renderable,
+    #[serde(default = "default_blocks_tile")]
+    pub blocks_tile: bool,
+    pub vision_range: i32,
+    pub stats: MobStats,
+    /// A dice string such as "1d6" or "d8+1".
+    #[serde(default = "default_damage")]
+    pub damage: String,
+    #[serde(default)]
+    pub armor: i32,
+    /// Lets the mob shoot at things, and makes it keep its distance.
+    pub ranged: Option<MobRanged>,
+    /// What the mob can cast, from the raws' spells.
+    #[serde(default)]
+    pub spells: Vec<MobSpell>,
+    /// Energy gained each turn; a normal action costs 100.
+    #[serde(default = "default_speed")]
+    pub speed: i32,
+    /// Added to the 2d6 morale roll the first time the mob is badly hurt.
+    #[serde(default)]
+    pub morale: i32,
+    /// Roams about when idle, rather than staying put.
+    #[serde(default)]
+    pub wanders: bool,
+    /// One of the factions, which decides who the mob attacks, ignores or runs from.
+    pub faction: String,
+    #[serde(default)]
+    pub tags: Vec<MonsterTag>,
+    /// Put on whoever the mob hurts.
+    pub inflicts_status: Option<InflictsStatus>,
+    /// Also put on whoever the mob hurts, until cured.
+    pub inflicts_debility: Option<Debility>,
+}
+
+fn default_blocks_tile() -> bool {
+    true
+}
+
+fn default_damage() -> String {
+    "1d6".to_string()
+}
+
+fn default_speed() -> i32 {
+    NORMAL_SPEED
+}
+
+/// A mob's own ranged attack: a dice string like `damage`, and how many tiles it reaches.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MobRanged {
+    pub damage: String,
+    pub range: i32,
+}
+
+/// A spell a mob knows, and how many of its goes it has to wait before casting it again.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MobSpell {
+    pub name: String,
+    pub cooldown: i32,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MobStats {
+    #[serde(default)]
+    pub str: i32,
+    #[serde(default)]
+    pub dex: i32,
+    #[serde(default)]
+    pub con: i32,
+    #[serde(default)]
+    pub int: i32,
+    #[serde(default)]
+    pub wis: i32,
+    #[serde(default)]
+    pub cha: i32,
+    pub hp: i32,
+}
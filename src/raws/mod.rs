@@ -0,0 +1,167 @@
+mod faction_structs;
+mod item_structs;
+mod mob_structs;
+mod prop_structs;
+mod rawmaster;
+mod spawn_table_structs;
+mod spell_structs;
+
+pub use rawmaster::*;
+use serde::Deserialize;
+
+/// Where designers keep the game's content. If it isn't there (say, a bare release binary), the
+/// copy built into the game is used instead.
+const RAW_FILE: &str = "./raws/spawns.json";
+const EMBEDDED_RAWS: &str = include_str!("../../raws/spawns.json");
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Raws {
+    pub items: Vec<item_structs::Item>,
+    pub mobs: Vec<mob_structs::Mob>,
+    pub props: Vec<prop_structs::Prop>,
+    pub factions: Vec<faction_structs::Faction>,
+    pub spells: Vec<spell_structs::Spell>,
+    pub spawn_table: Vec<spawn_table_structs::SpawnTableEntry>,
+}
+
+/// Everything that can go wrong loading the raw files, worded for whoever is editing them.
+#[derive(Debug)]
+pub struct RawError {
+    pub source: String,
+    pub problems: Vec<String>,
+}
+
+impl std::fmt::Display for RawError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Problems found in {}:", self.source)?;
+        for problem in self.problems.iter() {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RawError {}
+
+/// Reads, checks and indexes the raw files.
+pub fn load_raws() -> Result<RawMaster, RawError> {
+    let (source, contents) = match std::fs::read_to_string(RAW_FILE) {
+        Ok(contents) => (RAW_FILE.to_string(), contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            ("the built-in raws".to_string(), EMBEDDED_RAWS.to_string())
+        }
+        Err(e) => {
+            return Err(RawError {
+                source: RAW_FILE.to_string(),
+                problems: vec![format!("unable to read the file: {}", e)],
+            })
+        }
+    };
+
+    let raws: Raws = serde_json::from_str(&contents).map_err(|e| RawError {
+        source: source.clone(),
+        problems: vec![e.to_string()],
+    })?;
+    RawMaster::new(raws).map_err(|problems| RawError { source, problems })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rltk::RandomNumberGenerator;
+
+    fn built_in() -> Raws {
+        serde_json::from_str(EMBEDDED_RAWS).expect("the built-in raws should parse")
+    }
+
+    fn problems(raws: Raws) -> Vec<String> {
+        match RawMaster::new(raws) {
+            Ok(_) => Vec::new(),
+            Err(problems) => problems,
+        }
+    }
+
+    #[test]
+    fn the_built_in_raws_are_valid() {
+        assert_eq!(problems(built_in()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn duplicate_names_are_reported() {
+        let mut raws = built_in();
+        raws.mobs.push(raws.mobs[0].clone());
+        let name = raws.mobs[0].name.clone();
+        assert!(problems(raws)
+            .iter()
+            .any(|p| p.contains(name.as_str()) && p.contains("more than once")));
+    }
+
+    #[test]
+    fn spawn_table_mistakes_are_reported() {
+        let mut raws = built_in();
+        let mut entry = raws.spawn_table[0].clone();
+        entry.name = "Unicorn".to_string();
+        entry.min_depth = 5;
+        entry.max_depth = 2;
+        raws.spawn_table.push(entry);
+        let problems = problems(raws);
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("\"Unicorn\", which isn't an item, mob or prop")));
+        assert!(problems
+            .iter()
+            .any(|p| p.contains("min_depth deeper than its max_depth")));
+    }
+
+    #[test]
+    fn bad_mob_stats_are_reported() {
+        let mut raws = built_in();
+        raws.mobs[0].stats.hp = 0;
+        raws.mobs[0].vision_range = 0;
+        raws.mobs[0].damage = "lots".to_string();
+        raws.mobs[0].faction = "Nobody".to_string();
+        let name = raws.mobs[0].name.clone();
+        let problems = problems(raws);
+        for expected in [
+            "needs at least 1 hp",
+            "needs a vision_range of at least 1",
+            "which isn't a dice roll",
+            "the faction \"Nobody\", which isn't defined",
+        ] {
+            assert!(
+                problems
+                    .iter()
+                    .any(|p| p.contains(name.as_str()) && p.contains(expected)),
+                "missing \"{}\" in {:?}",
+                expected,
+                problems
+            );
+        }
+    }
+
+    #[test]
+    fn the_spawn_table_keeps_to_its_depths() {
+        let mut raws = built_in();
+        for entry in raws.spawn_table.iter_mut() {
+            entry.min_depth = 3;
+            entry.max_depth = 3;
+        }
+        let raws = RawMaster::new(raws).expect("the raws should still be valid");
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(raws.spawn_table_for_depth(2).roll(&mut rng), "None");
+        assert_ne!(raws.spawn_table_for_depth(3).roll(&mut rng), "None");
+    }
+
+    #[test]
+    fn errors_name_their_source() {
+        let error = RawError {
+            source: "spawns.json".to_string(),
+            problems: vec!["one".to_string(), "two".to_string()],
+        };
+        assert_eq!(
+            error.to_string(),
+            "Problems found in spawns.json:\n  - one\n  - two\n"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use super::super::TrapKind;
+use super::item_structs::Renderable;
+use serde::Deserialize;
+
+/// Fixtures that aren't monsters and can't be picked up, such as traps.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Prop {
+    pub name: String,
+    pub renderable: Renderable,
+    #[serde(default)]
+    pub hidden: bool,
+    pub trap: Option<TrapKind>,
+}
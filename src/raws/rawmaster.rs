@@ -0,0 +1,662 @@
+use super::super::{
+    character::CharacterClass, random_table::RandomTable, AiState, Ammo, AreaOfEffect, Armor,
+    ArmorKind, BlocksTile, CombatStats, Consumable, CuresDebilities, Energy, EquipmentSlot,
+    Equippable, Faction, Hidden, InflictsDamage, InflictsDebility, InflictsStatus, Item, Key,
+    KnownSpell, MeleePowerBonus, Monster, MonsterTags, Morale, Name, NaturalArmor, NaturalAttack,
+    NaturalRangedAttack, Position, ProvidesHealing, Ranged, Reaction, Renderable, SerializeMe,
+    Trap, Viewshed, Weapon, WeaponTag, PLAYER_FACTION,
+};
+use super::{item_structs, mob_structs, prop_structs, spell_structs, Raws};
+use rltk::RGB;
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+use std::collections::HashMap;
+
+/// The loaded raw files, indexed by name. Lives in the ECS as a resource.
+pub struct RawMaster {
+    raws: Raws,
+    item_index: HashMap<String, usize>,
+    mob_index: HashMap<String, usize>,
+    prop_index: HashMap<String, usize>,
+    faction_index: HashMap<String, usize>,
+    spell_index: HashMap<String, usize>,
+}
+
+/// The key in a faction's reactions for every faction it doesn't list.
+const DEFAULT_REACTION: &str = "Default";
+
+impl RawMaster {
+    /// Checks and indexes the raws, or returns every problem found with them.
+    pub fn new(raws: Raws) -> Result<RawMaster, Vec<String>> {
+        let mut problems: Vec<String> = Vec::new();
+        let mut item_index = HashMap::new();
+        let mut mob_index = HashMap::new();
+        let mut prop_index = HashMap::new();
+        let mut faction_index = HashMap::new();
+        let mut spell_index = HashMap::new();
+
+        for (i, faction) in raws.factions.iter().enumerate() {
+            if faction.name.is_empty() {
+                problems.push("found a faction with an empty name".to_string());
+            } else if faction.name == DEFAULT_REACTION || faction.name == PLAYER_FACTION {
+                problems.push(format!(
+                    "\"{}\" is kept for the reaction table, so can't name a faction",
+                    faction.name
+                ));
+            } else if faction_index.insert(faction.name.clone(), i).is_some() {
+                problems.push(format!(
+                    "the faction \"{}\" is defined more than once",
+                    faction.name
+                ));
+            }
+        }
+        for faction in raws.factions.iter() {
+            for other in faction.reactions.keys() {
+                if other != DEFAULT_REACTION
+                    && other != PLAYER_FACTION
+                    && !faction_index.contains_key(other)
+                {
+                    problems.push(format!(
+                        "the faction \"{}\" has a reaction to \"{}\", which isn't a faction",
+                        faction.name, other
+                    ));
+                }
+            }
+        }
+
+        for (i, item) in raws.items.iter().enumerate() {
+            check_name(
+                &item.name,
+                &item_index,
+                &mob_index,
+                &prop_index,
+                &mut problems,
+            );
+            check_renderable(&item.name, &item.renderable, &mut problems);
+            if item.area_of_effect.is_some() && item.ranged.is_none() {
+                problems.push(format!(
+                    "item \"{}\" has an area_of_effect but isn't ranged",
+                    item.name
+                ));
+            }
+            if item.ranged.is_some()
+                && item.inflicts_damage.is_none()
+                && item.inflicts_status.is_none()
+            {
+                problems.push(format!(
+                    "item \"{}\" is ranged but does nothing to its target (give it inflicts_damage or inflicts_status)",
+                    item.name
+                ));
+            }
+            check_status(&item.name, &item.inflicts_status, &mut problems);
+            if item.ammo.as_ref().is_some_and(|ammo| ammo.count < 1) {
+                problems.push(format!(
+                    "item \"{}\" is ammo, but needs a count of at least 1",
+                    item.name
+                ));
+            }
+            if let Some(equippable) = &item.equippable {
+                if equippable.melee_power_bonus.is_some()
+                    && !matches!(
+                        equippable.slot,
+                        EquipmentSlot::Melee | EquipmentSlot::Ring | EquipmentSlot::Amulet
+                    )
+                {
+                    problems.push(format!(
+                        "item \"{}\" has a melee_power_bonus, but only weapons, rings and amulets can",
+                        item.name
+                    ));
+                }
+                let weapon = matches!(
+                    equippable.slot,
+                    EquipmentSlot::Melee | EquipmentSlot::Ranged
+                );
+                if weapon {
+                    match equippable.damage.as_deref().map(parse_damage) {
+                        None => problems.push(format!(
+                            "item \"{}\" is a {:?} weapon but has no damage",
+                            item.name, equippable.slot
+                        )),
+                        Some(Some(dice)) if dice.n_dice >= 1 && dice.die_type >= 1 => {}
+                        Some(_) => problems.push(format!(
+                            "item \"{}\" has the damage \"{}\", which isn't a dice roll like \"1d6\"",
+                            item.name,
+                            equippable.damage.as_deref().unwrap_or_default()
+                        )),
+                    }
+                } else if equippable.damage.is_some() || !equippable.tags.is_empty() {
+                    problems.push(format!(
+                        "item \"{}\" has weapon damage or tags but isn't a weapon",
+                        item.name
+                    ));
+                }
+                let shoots = equippable.tags.contains(&WeaponTag::Near)
+                    || equippable.tags.contains(&WeaponTag::Far);
+                if equippable.slot == EquipmentSlot::Ranged {
+                    if !shoots {
+                        problems.push(format!(
+                            "item \"{}\" is a Ranged weapon, so it needs the Near or Far tag",
+                            item.name
+                        ));
+                    }
+                    if equippable.ammo.is_none() {
+                        problems.push(format!(
+                            "item \"{}\" is a Ranged weapon but has no ammo kind",
+                            item.name
+                        ));
+                    }
+                } else if shoots || equippable.ammo.is_some() {
+                    problems.push(format!(
+                        "item \"{}\" has a range or ammo but isn't a Ranged weapon",
+                        item.name
+                    ));
+                }
+                match equippable.armor {
+                    Some(_) if weapon => problems.push(format!(
+                        "item \"{}\" is a {:?} weapon, so it can't be armor",
+                        item.name, equippable.slot
+                    )),
+                    Some(ArmorKind::Shield) if equippable.slot != EquipmentSlot::OffHand => {
+                        problems.push(format!(
+                            "item \"{}\" is a Shield, so it belongs in the OffHand slot",
+                            item.name
+                        ))
+                    }
+                    None if equippable.armor_points.is_some() => problems.push(format!(
+                        "item \"{}\" has armor_points but no armor kind",
+                        item.name
+                    )),
+                    _ => {}
+                }
+            }
+            item_index.insert(item.name.clone(), i);
+        }
+
+        for class in CharacterClass::ALL.iter() {
+            for (name, _equipped) in class.starting_equipment().iter() {
+                if !item_index.contains_key(*name) {
+                    problems.push(format!(
+                        "the {} starts with \"{}\", which isn't an item",
+                        class.name(),
+                        name
+                    ));
+                }
+            }
+        }
+
+        for (i, spell) in raws.spells.iter().enumerate() {
+            if spell.name.is_empty() {
+                problems.push("found a spell with an empty name".to_string());
+            } else if spell_index.insert(spell.name.clone(), i).is_some() {
+                problems.push(format!(
+                    "the spell \"{}\" is defined more than once",
+                    spell.name
+                ));
+            }
+            if spell.range < 1 {
+                problems.push(format!(
+                    "spell \"{}\" needs a range of at least 1",
+                    spell.name
+                ));
+            }
+            if spell.inflicts_damage.is_none() && spell.inflicts_status.is_none() {
+                problems.push(format!(
+                    "spell \"{}\" does nothing to its target (give it inflicts_damage or inflicts_status)",
+                    spell.name
+                ));
+            }
+            check_status(&spell.name, &spell.inflicts_status, &mut problems);
+        }
+
+        for (i, mob) in raws.mobs.iter().enumerate() {
+            check_name(
+                &mob.name,
+                &item_index,
+                &mob_index,
+                &prop_index,
+                &mut problems,
+            );
+            check_renderable(&mob.name, &mob.renderable, &mut problems);
+            if mob.stats.hp < 1 {
+                problems.push(format!("mob \"{}\" needs at least 1 hp", mob.name));
+            }
+            if mob.vision_range < 1 {
+                problems.push(format!(
+                    "mob \"{}\" needs a vision_range of at least 1",
+                    mob.name
+                ));
+            }
+            match parse_damage(&mob.damage) {
+                Some(dice) if dice.n_dice >= 1 && dice.die_type >= 1 => {}
+                _ => problems.push(format!(
+                    "mob \"{}\" has the damage \"{}\", which isn't a dice roll like \"1d6\"",
+                    mob.name, mob.damage
+                )),
+            }
+            if mob.armor < 0 {
+                problems.push(format!("mob \"{}\" can't have negative armor", mob.name));
+            }
+            if mob.speed < 1 {
+                problems.push(format!("mob \"{}\" needs a speed of at least 1", mob.name));
+            }
+            if let Some(ranged) = &mob.ranged {
+                if parse_damage(&ranged.damage).is_none() {
+                    problems.push(format!(
+                        "mob \"{}\" has the ranged damage \"{}\", which isn't a dice roll like \"1d6\"",
+                        mob.name, ranged.damage
+                    ));
+                }
+                if ranged.range < 2 {
+                    problems.push(format!(
+                        "mob \"{}\" needs a ranged attack reaching at least 2 tiles",
+                        mob.name
+                    ));
+                }
+            }
+            for spell in mob.spells.iter() {
+                if !spell_index.contains_key(&spell.name) {
+                    problems.push(format!(
+                        "mob \"{}\" knows the spell \"{}\", which isn't defined",
+                        mob.name, spell.name
+                    ));
+                }
+                if spell.cooldown < 1 {
+                    problems.push(format!(
+                        "mob \"{}\" needs a cooldown of at least 1 for \"{}\"",
+                        mob.name, spell.name
+                    ));
+                }
+            }
+            if !faction_index.contains_key(&mob.faction) {
+                problems.push(format!(
+                    "mob \"{}\" is in the faction \"{}\", which isn't defined",
+                    mob.name, mob.faction
+                ));
+            }
+            check_status(&mob.name, &mob.inflicts_status, &mut problems);
+            mob_index.insert(mob.name.clone(), i);
+        }
+
+        for (i, prop) in raws.props.iter().enumerate() {
+            check_name(
+                &prop.name,
+                &item_index,
+                &mob_index,
+                &prop_index,
+                &mut problems,
+            );
+            check_renderable(&prop.name, &prop.renderable, &mut problems);
+            prop_index.insert(prop.name.clone(), i);
+        }
+
+        for entry in raws.spawn_table.iter() {
+            if !item_index.contains_key(&entry.name)
+                && !mob_index.contains_key(&entry.name)
+                && !prop_index.contains_key(&entry.name)
+            {
+                problems.push(format!(
+                    "the spawn table lists \"{}\", which isn't an item, mob or prop",
+                    entry.name
+                ));
+            }
+            if entry.min_depth > entry.max_depth {
+                problems.push(format!(
+                    "the spawn table entry for \"{}\" has a min_depth deeper than its max_depth",
+                    entry.name
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(RawMaster {
+                raws,
+                item_index,
+                mob_index,
+                prop_index,
+                faction_index,
+                spell_index,
+            })
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Builds the table of what might turn up on a level at `depth`.
+    pub fn spawn_table_for_depth(&self, depth: i32) -> RandomTable {
+        self.raws
+            .spawn_table
+            .iter()
+            .filter(|entry| depth >= entry.min_depth && depth <= entry.max_depth)
+            .fold(RandomTable::new(), |table, entry| {
+                table.add(&entry.name, entry.weight + entry.depth_weight * depth)
+            })
+    }
+
+    /// How members of the faction `mine` treat members of `theirs`: as the faction's table says,
+    /// falling back on its `Default`, or ignoring them if it has neither.
+    pub fn reaction(&self, mine: &str, theirs: &str) -> Reaction {
+        self.faction_index
+            .get(mine)
+            .map(|i| &self.raws.factions[*i].reactions)
+            .and_then(|reactions| {
+                reactions
+                    .get(theirs)
+                    .or_else(|| reactions.get(DEFAULT_REACTION))
+            })
+            .copied()
+            .unwrap_or(Reaction::Ignore)
+    }
+
+    fn item(&self, name: &str) -> Option<&item_structs::Item> {
+        self.item_index.get(name).map(|i| &self.raws.items[*i])
+    }
+
+    fn mob(&self, name: &str) -> Option<&mob_structs::Mob> {
+        self.mob_index.get(name).map(|i| &self.raws.mobs[*i])
+    }
+
+    fn spell(&self, name: &str) -> Option<&spell_structs::Spell> {
+        self.spell_index.get(name).map(|i| &self.raws.spells[*i])
+    }
+
+    fn prop(&self, name: &str) -> Option<&prop_structs::Prop> {
+        self.prop_index.get(name).map(|i| &self.raws.props[*i])
+    }
+}
+
+fn check_name(
+    name: &str,
+    item_index: &HashMap<String, usize>,
+    mob_index: &HashMap<String, usize>,
+    prop_index: &HashMap<String, usize>,
+    problems: &mut Vec<String>,
+) {
+    if name.is_empty() {
+        problems.push("found an entry with an empty name".to_string());
+    } else if item_index.contains_key(name)
+        || mob_index.contains_key(name)
+        || prop_index.contains_key(name)
+    {
+        problems.push(format!("\"{}\" is defined more than once", name));
+    }
+}
+
+fn check_status(
+    name: &str,
+    inflicts: &Option<item_structs::InflictsStatus>,
+    problems: &mut Vec<String>,
+) {
+    if let Some(inflicts) = inflicts {
+        if inflicts.turns < 1 {
+            problems.push(format!(
+                "\"{}\" inflicts {} for {} turns, but it needs to last at least one",
+                name,
+                inflicts.status.name(),
+                inflicts.turns
+            ));
+        }
+    }
+}
+
+fn check_renderable(name: &str, renderable: &item_structs::Renderable, problems: &mut Vec<String>) {
+    let mut glyph = renderable.glyph.chars();
+    match (glyph.next(), glyph.next()) {
+        (Some(c), None) => {
+            if rltk::to_cp437(c) == 0 {
+                problems.push(format!(
+                    "\"{}\" has the glyph '{}', which isn't in the font",
+                    name, c
+                ));
+            }
+        }
+        _ => problems.push(format!(
+            "\"{}\" needs a glyph of exactly one character, not \"{}\"",
+            name, renderable.glyph
+        )),
+    }
+    for colour in [&renderable.fg, &renderable.bg].iter() {
+        if RGB::from_hex(colour).is_err() {
+            problems.push(format!(
+                "\"{}\" has the colour \"{}\", which isn't in #RRGGBB form",
+                name, colour
+            ));
+        }
+    }
+}
+
+/// Reads a damage dice string. Dungeon World writes a single die as just "d8", so that is
+/// accepted as well as "1d8".
+fn parse_damage(damage: &str) -> Option<rltk::DiceType> {
+    let damage = damage.trim();
+    let damage = if damage.starts_with('d') {
+        format!("1{}", damage)
+    } else {
+        damage.to_string()
+    };
+    rltk::parse_dice_string(&damage).ok()
+}
+
+fn get_renderable_component(renderable: &item_structs::Renderable) -> Renderable {
+    Renderable {
+        glyph: rltk::to_cp437(renderable.glyph.chars().next().unwrap()),
+        fg: RGB::from_hex(&renderable.fg).expect("Colour checked at load"),
+        bg: RGB::from_hex(&renderable.bg).expect("Colour checked at load"),
+        render_order: renderable.order,
+    }
+}
+
+/// Spawns whatever the raws call `name` at (x, y), or returns `None` if they don't know it.
+pub fn spawn_named_entity(ecs: &mut World, name: &str, x: i32, y: i32) -> Option<Entity> {
+    let (item, mob, prop) = {
+        let raws = ecs.fetch::<RawMaster>();
+        (
+            raws.item(name).cloned(),
+            raws.mob(name).cloned(),
+            raws.prop(name).cloned(),
+        )
+    };
+
+    if let Some(item) = item {
+        Some(spawn_item(ecs, &item, x, y))
+    } else if let Some(mob) = mob {
+        Some(spawn_mob(ecs, &mob, x, y))
+    } else {
+        prop.map(|prop| spawn_prop(ecs, &prop, x, y))
+    }
+}
+
+fn spawn_item(ecs: &mut World, item: &item_structs::Item, x: i32, y: i32) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(get_renderable_component(&item.renderable))
+        .with(Name {
+            name: item.name.clone(),
+        })
+        .with(Item {});
+
+    if item.consumable {
+        builder = builder.with(Consumable {});
+    }
+    if let Some(heal_amount) = item.provides_healing {
+        builder = builder.with(ProvidesHealing { heal_amount });
+    }
+    if let Some(range) = item.ranged {
+        builder = builder.with(Ranged { range });
+    }
+    if let Some(damage) = item.inflicts_damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    if let Some(radius) = item.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(inflicts) = &item.inflicts_status {
+        builder = builder.with(InflictsStatus {
+            status: inflicts.status,
+            turns: inflicts.turns,
+        });
+    }
+    if item.cures_debilities {
+        builder = builder.with(CuresDebilities {});
+    }
+    if let Some(equippable) = &item.equippable {
+        builder = builder.with(Equippable {
+            slot: equippable.slot,
+        });
+        if let Some(bonus) = equippable.melee_power_bonus {
+            builder = builder.with(MeleePowerBonus { bonus });
+        }
+        if let Some(kind) = equippable.armor {
+            builder = builder.with(Armor {
+                kind,
+                points: equippable.armor_points.unwrap_or(kind.default_points()),
+            });
+        }
+        if let Some(damage) = &equippable.damage {
+            builder = builder.with(Weapon {
+                damage: parse_damage(damage).expect("Damage checked at load"),
+                tags: equippable.tags.clone(),
+                ammo: equippable.ammo,
+            });
+        }
+    }
+    if let Some(ammo) = &item.ammo {
+        builder = builder.with(Ammo {
+            kind: ammo.kind,
+            count: ammo.count,
+        });
+    }
+    if item.key {
+        builder = builder.with(Key {});
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
+}
+
+fn spawn_mob(ecs: &mut World, mob: &mob_structs::Mob, x: i32, y: i32) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(get_renderable_component(&mob.renderable))
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: mob.vision_range,
+            dirty: true,
+        })
+        .with(Monster {})
+        .with(Name {
+            name: mob.name.clone(),
+        })
+        .with(CombatStats {
+            str: mob.stats.str,
+            dex: mob.stats.dex,
+            con: mob.stats.con,
+            int: mob.stats.int,
+            wis: mob.stats.wis,
+            cha: mob.stats.cha,
+            max_hp: mob.stats.hp,
+            hp: mob.stats.hp,
+        })
+        .with(Energy::new(mob.speed))
+        .with(if mob.wanders {
+            AiState::Wander
+        } else {
+            AiState::Idle
+        })
+        .with(Morale {
+            morale: mob.morale,
+            tested: false,
+        })
+        .with(Faction {
+            name: mob.faction.clone(),
+        });
+
+    if mob.blocks_tile {
+        builder = builder.with(BlocksTile {});
+    }
+    builder = builder.with(NaturalAttack {
+        damage: parse_damage(&mob.damage).expect("Damage checked at load"),
+    });
+    if mob.armor > 0 {
+        builder = builder.with(NaturalArmor { armor: mob.armor });
+    }
+    if !mob.tags.is_empty() {
+        builder = builder.with(MonsterTags {
+            tags: mob.tags.clone(),
+        });
+    }
+    if let Some(inflicts) = &mob.inflicts_status {
+        builder = builder.with(InflictsStatus {
+            status: inflicts.status,
+            turns: inflicts.turns,
+        });
+    }
+    if let Some(debility) = mob.inflicts_debility {
+        builder = builder.with(InflictsDebility { debility });
+    }
+    if let Some(ranged) = &mob.ranged {
+        builder = builder.with(NaturalRangedAttack {
+            damage: parse_damage(&ranged.damage).expect("Damage checked at load"),
+            range: ranged.range,
+        });
+    }
+    let caster = builder.marked::<SimpleMarker<SerializeMe>>().build();
+
+    for known in mob.spells.iter() {
+        let spell = ecs
+            .fetch::<RawMaster>()
+            .spell(&known.name)
+            .cloned()
+            .expect("Spells checked at load");
+        spawn_spell(ecs, &spell, caster, known.cooldown);
+    }
+
+    caster
+}
+
+/// Gives `caster` a spell, as an entity carrying the effects a scroll of it would.
+fn spawn_spell(ecs: &mut World, spell: &spell_structs::Spell, caster: Entity, cooldown: i32) {
+    let mut builder = ecs
+        .create_entity()
+        .with(Name {
+            name: spell.name.clone(),
+        })
+        .with(Ranged { range: spell.range })
+        .with(KnownSpell {
+            caster,
+            cooldown,
+            ready_in: 0,
+        });
+    if let Some(damage) = spell.inflicts_damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    if let Some(radius) = spell.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(inflicts) = &spell.inflicts_status {
+        builder = builder.with(InflictsStatus {
+            status: inflicts.status,
+            turns: inflicts.turns,
+        });
+    }
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
+}
+
+fn spawn_prop(ecs: &mut World, prop: &prop_structs::Prop, x: i32, y: i32) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(get_renderable_component(&prop.renderable))
+        .with(Name {
+            name: prop.name.clone(),
+        });
+
+    if prop.hidden {
+        builder = builder.with(Hidden {});
+    }
+    if let Some(kind) = prop.trap {
+        builder = builder.with(Trap { kind });
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
+}
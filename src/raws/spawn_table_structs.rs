@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// One line of the spawn table. The chance of `name` turning up at a given depth is
+/// `weight + depth_weight * depth`; anything at or below zero never spawns.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTableEntry {
+    pub name: String,
+    pub weight: i32,
+    #[serde(default)]
+    pub depth_weight: i32,
+    #[serde(default = "default_min_depth")]
+    pub min_depth: i32,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: i32,
+}
+
+fn default_min_depth() -> i32 {
+    1
+}
+
+fn default_max_depth() -> i32 {
+    i32::MAX
+}
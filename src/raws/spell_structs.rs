@@ -0,0 +1,13 @@
+use super::item_structs::InflictsStatus;
+use serde::Deserialize;
+
+/// Magic monsters can cast. A spell works like a scroll, only it's never used up.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Spell {
+    pub name: String,
+    pub range: i32,
+    pub inflicts_damage: Option<i32>,
+    pub area_of_effect: Option<i32>,
+    pub inflicts_status: Option<InflictsStatus>,
+}
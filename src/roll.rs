@@ -0,0 +1,31 @@
+use rltk::RandomNumberGenerator;
+
+/// How a 2d6 roll turned out, World of Dungeons style: 10+ is a clean success, 7-9 gets there
+/// at a cost, and 6- is a miss.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Outcome {
+    Success,
+    Partial,
+    Miss,
+}
+
+impl Outcome {
+    pub fn from_total(total: i32) -> Outcome {
+        if total >= 10 {
+            Outcome::Success
+        } else if total >= 7 {
+            Outcome::Partial
+        } else {
+            Outcome::Miss
+        }
+    }
+}
+
+/// A roll this low is a miss bad enough to leave a lasting mark.
+pub const BAD_MISS: i32 = 3;
+
+/// Rolls 2d6 plus a stat (or other modifier), returning the total along with its outcome.
+pub fn roll_2d6(rng: &mut RandomNumberGenerator, modifier: i32) -> (i32, Outcome) {
+    let total = rng.roll_dice(2, 6) + modifier;
+    (total, Outcome::from_total(total))
+}
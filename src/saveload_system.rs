@@ -29,9 +29,18 @@ pub fn save_game(_ecs: &mut World) {}
 pub fn save_game(ecs: &mut World) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let seed = ecs.fetch::<super::RunSeed>().seed;
+    let dungeon = ecs
+        .get_mut::<super::dungeon::MasterDungeonMap>()
+        .unwrap()
+        .clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            seed,
+            dungeon,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -49,8 +58,10 @@ pub fn save_game(ecs: &mut World) {
             serializer,
             data,
             Position,
+            OtherLevelPosition,
             Renderable,
             Player,
+            PartyMember,
             Viewshed,
             Monster,
             Name,
@@ -63,7 +74,7 @@ pub fn save_game(ecs: &mut World) {
             Ranged,
             InflictsDamage,
             AreaOfEffect,
-            Confusion,
+            InflictsStatus,
             ProvidesHealing,
             InBackpack,
             WantsToPickupItem,
@@ -73,8 +84,47 @@ pub fn save_game(ecs: &mut World) {
             Equippable,
             Equipped,
             MeleePowerBonus,
-            ArmorBonus,
-            WantsToRemoveItem
+            Armor,
+            WantsToRemoveItem,
+            BlocksVisibility,
+            Door,
+            Locked,
+            Key,
+            Hidden,
+            Trap,
+            EntityMoved,
+            WantsToSearch,
+            Alerted,
+            Class,
+            Skills,
+            Abilities,
+            Experience,
+            NaturalAttack,
+            NaturalArmor,
+            MonsterTags,
+            Weapon,
+            StatusEffects,
+            Debilities,
+            InflictsDebility,
+            CuresDebilities,
+            DeathsMark,
+            Ammo,
+            WantsToShoot,
+            Initiative,
+            Guarding,
+            WantsToDelay,
+            Energy,
+            AiState,
+            Morale,
+            MakesNoise,
+            Sneaking,
+            Travelling,
+            Faction,
+            Reputation,
+            NaturalRangedAttack,
+            KnownSpell,
+            TakingAim,
+            Casting
         );
     }
 
@@ -128,8 +178,10 @@ pub fn load_game(ecs: &mut World) {
             de,
             d,
             Position,
+            OtherLevelPosition,
             Renderable,
             Player,
+            PartyMember,
             Viewshed,
             Monster,
             Name,
@@ -142,7 +194,7 @@ pub fn load_game(ecs: &mut World) {
             Ranged,
             InflictsDamage,
             AreaOfEffect,
-            Confusion,
+            InflictsStatus,
             ProvidesHealing,
             InBackpack,
             WantsToPickupItem,
@@ -152,12 +204,52 @@ pub fn load_game(ecs: &mut World) {
             Equippable,
             Equipped,
             MeleePowerBonus,
-            ArmorBonus,
-            WantsToRemoveItem
+            Armor,
+            WantsToRemoveItem,
+            BlocksVisibility,
+            Door,
+            Locked,
+            Key,
+            Hidden,
+            Trap,
+            EntityMoved,
+            WantsToSearch,
+            Alerted,
+            Class,
+            Skills,
+            Abilities,
+            Experience,
+            NaturalAttack,
+            NaturalArmor,
+            MonsterTags,
+            Weapon,
+            StatusEffects,
+            Debilities,
+            InflictsDebility,
+            CuresDebilities,
+            DeathsMark,
+            Ammo,
+            WantsToShoot,
+            Initiative,
+            Guarding,
+            WantsToDelay,
+            Energy,
+            AiState,
+            Morale,
+            MakesNoise,
+            Sneaking,
+            Travelling,
+            Faction,
+            Reputation,
+            NaturalRangedAttack,
+            KnownSpell,
+            TakingAim,
+            Casting
         );
     }
 
     let mut deleteme: Option<Entity> = None;
+    let mut player_entity: Option<Entity> = None;
     {
         let entities = ecs.entities();
         let helper = ecs.read_storage::<SerializationHelper>();
@@ -166,16 +258,25 @@ pub fn load_game(ecs: &mut World) {
         for (e, h) in (&entities, &helper).join() {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
-            worldmap.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+            worldmap.tile_content = vec![Vec::new(); worldmap.tiles.len()];
+            let mut seed = ecs.write_resource::<super::RunSeed>();
+            *seed = super::RunSeed::new(h.seed);
+            let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+            *rng = seed.rng_for_depth(worldmap.depth);
+            let mut dungeon = ecs.write_resource::<super::dungeon::MasterDungeonMap>();
+            *dungeon = h.dungeon.clone();
+            let mut dijkstra_maps = ecs.write_resource::<super::dijkstra_maps::DijkstraMaps>();
+            *dijkstra_maps = super::dijkstra_maps::DijkstraMaps::default();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
             let mut ppos = ecs.write_resource::<rltk::Point>();
             *ppos = rltk::Point::new(pos.x, pos.y);
-            let mut player_resource = ecs.write_resource::<Entity>();
-            *player_resource = e;
+            player_entity = Some(e);
         }
     }
+    // There may not be a player resource yet if the game was loaded straight from the menu
+    ecs.insert(player_entity.expect("Save has no player"));
     ecs.delete_entity(deleteme.unwrap())
         .expect("Unable to delete helper");
 }
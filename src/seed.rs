@@ -0,0 +1,88 @@
+use rltk::RandomNumberGenerator;
+
+/// The seed for the current run. Each level gets its own generator derived from the seed and
+/// the depth, so a level comes out the same no matter what happened on the levels before it.
+#[derive(Copy, Clone)]
+pub struct RunSeed {
+    pub seed: u64,
+}
+
+impl RunSeed {
+    pub fn new(seed: u64) -> RunSeed {
+        RunSeed { seed }
+    }
+
+    /// Picks a fresh seed for a new run.
+    pub fn random() -> RunSeed {
+        RunSeed::new(RandomNumberGenerator::new().next_u64())
+    }
+
+    /// Looks for `--seed <number>` on the command line.
+    pub fn from_args() -> Option<RunSeed> {
+        let args: Vec<String> = std::env::args().collect();
+        args.iter()
+            .position(|arg| arg == "--seed")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|seed| seed.parse::<u64>().ok())
+            .map(RunSeed::new)
+    }
+
+    /// The generator used to build (and populate) the level at `depth`.
+    pub fn rng_for_depth(&self, depth: i32) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(mix(self.seed ^ (depth as u64).wrapping_mul(GOLDEN_GAMMA)))
+    }
+}
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// SplitMix64 finalizer, so neighbouring depths don't get neighbouring seeds.
+fn mix(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::map_builders::random_builder;
+    use super::*;
+
+    fn rolls(rng: &mut RandomNumberGenerator) -> Vec<u64> {
+        (0..8).map(|_| rng.next_u64()).collect()
+    }
+
+    #[test]
+    fn same_seed_and_depth_give_the_same_generator() {
+        let seed = RunSeed::new(1234);
+        assert_eq!(
+            rolls(&mut seed.rng_for_depth(3)),
+            rolls(&mut seed.rng_for_depth(3))
+        );
+    }
+
+    #[test]
+    fn depths_and_seeds_get_their_own_generators() {
+        let seed = RunSeed::new(1234);
+        assert_ne!(
+            rolls(&mut seed.rng_for_depth(1)),
+            rolls(&mut seed.rng_for_depth(2))
+        );
+        assert_ne!(
+            rolls(&mut seed.rng_for_depth(1)),
+            rolls(&mut RunSeed::new(1235).rng_for_depth(1))
+        );
+    }
+
+    #[test]
+    fn a_seed_builds_the_same_level_every_time() {
+        let seed = RunSeed::new(1234);
+        let build = || {
+            let mut rng = seed.rng_for_depth(2);
+            let mut builder = random_builder(2, &mut rng);
+            builder.build_map(&mut rng);
+            builder.get_map().tiles
+        };
+        assert!(build() == build());
+    }
+}
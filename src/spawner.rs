@@ -1,30 +1,43 @@
 use super::{
-    map::MAPWIDTH, random_table::RandomTable, AreaOfEffect, ArmorBonus, BlocksTile, CombatStats,
-    Confusion, Consumable, EquipmentSlot, Equippable, InflictsDamage, Item, MeleePowerBonus,
-    Monster, Name, Player, Position, ProvidesHealing, Ranged, Rect, Renderable, SerializeMe,
-    Viewshed,
+    character::CharacterDraft,
+    raws::{spawn_named_entity, RawMaster},
+    Abilities, BlocksTile, BlocksVisibility, Class, CombatStats, Door, Energy, Experience, Faction,
+    Locked, Map, Name, PartyMember, Position, Renderable, Reputation, SerializeMe, Skills,
+    Viewshed, NORMAL_SPEED, PLAYER_FACTION,
 };
 use rltk::{RandomNumberGenerator, RGB};
 use specs::prelude::*;
 use specs::saveload::{MarkedBuilder, SimpleMarker};
-use std::collections::HashMap;
 
-/// Spawns the player and returns his/her entity object.
-pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
-    let stats: CombatStats;
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        stats = CombatStats {
-            str: rng.roll_dice(1, 4) - 1,
-            dex: rng.roll_dice(1, 4) - 1,
-            con: rng.roll_dice(1, 4) - 1,
-            int: rng.roll_dice(1, 4) - 1,
-            wis: rng.roll_dice(1, 4) - 1,
-            cha: rng.roll_dice(1, 4) - 1,
-            max_hp: 30, // TODO calculate based on con
-            hp: 30,
-        };
-    }
+/// Each place in the marching order has its own colour, so the party can be told apart.
+const PARTY_COLOURS: [(u8, u8, u8); 6] = [
+    rltk::YELLOW,
+    rltk::LIGHT_BLUE,
+    rltk::PINK,
+    rltk::LIGHT_GREEN,
+    rltk::WHEAT,
+    rltk::VIOLET,
+];
+
+/// Spawns one of the party as they were made on the character creation screens, `slot` places
+/// along the marching order, and returns their entity object. Nobody is under control yet.
+pub fn party_member(
+    ecs: &mut World,
+    player_x: i32,
+    player_y: i32,
+    character: &CharacterDraft,
+    slot: usize,
+) -> Entity {
+    let stats = CombatStats {
+        str: character.stats[0],
+        dex: character.stats[1],
+        con: character.stats[2],
+        int: character.stats[3],
+        wis: character.stats[4],
+        cha: character.stats[5],
+        max_hp: character.max_hp(),
+        hp: character.max_hp(),
+    };
     ecs.create_entity()
         .with(Position {
             x: player_x,
@@ -32,292 +45,99 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
         })
         .with(Renderable {
             glyph: rltk::to_cp437('@'),
-            fg: RGB::named(rltk::YELLOW),
+            fg: RGB::named(PARTY_COLOURS[slot % PARTY_COLOURS.len()]),
             bg: RGB::named(rltk::BLACK),
             render_order: 0,
         })
-        .with(Player {})
+        .with(PartyMember { slot })
+        .with(BlocksTile {})
         .with(Viewshed {
             visible_tiles: Vec::new(),
             range: 8,
             dirty: true,
         })
         .with(Name {
-            name: "Player".to_string(),
+            name: character.name.clone(),
         })
         .with(stats)
+        .with(Class {
+            class: character.class,
+        })
+        .with(Skills {
+            skills: character.skills.clone(),
+        })
+        .with(Abilities {
+            abilities: character.class.starting_abilities(),
+        })
+        .with(Experience { level: 1, xp: 0 })
+        .with(Energy::new(NORMAL_SPEED))
+        .with(Faction {
+            name: PLAYER_FACTION.to_string(),
+        })
+        .with(Reputation::default())
         .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
 const MAX_MONSTERS: i32 = 4;
 
-fn room_table(map_depth: i32) -> RandomTable {
-    RandomTable::new()
-        .add("Rat", 10 - map_depth)
-        .add("Goblin", 1 + map_depth)
-        .add("Orc", map_depth - 1)
-        .add("Health Potion", 4)
-        .add("Fireball Scroll", 1)
-        .add("Confusion Scroll", 1)
-        .add("Magic Missile Scroll", 3)
-        .add("Dagger", 2)
-        .add("Shield", 2)
-        .add("Claymore", map_depth - 1)
-}
-
-/// Fills a room with stuff!
-#[allow(clippy::map_entry)]
-pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
-    let spawn_table = room_table(map_depth);
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
-
-    // Scope to keep the borrow checker happy
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_spawns = rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3;
-
-        for _i in 0..num_spawns {
-            let mut added = false;
-            let mut tries = 0;
-            while !added && tries < 20 {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                let idx = (y * MAPWIDTH) + x;
-                if !spawn_points.contains_key(&idx) {
-                    spawn_points.insert(idx, spawn_table.roll(&mut rng));
-                    added = true;
-                } else {
-                    tries += 1;
-                }
-            }
-        }
+/// Fills a region of the map (a list of tile indices) with stuff! Rolls come from the level's
+/// generator rather than the game-wide one, so a seed always populates a level the same way.
+pub fn spawn_region(
+    ecs: &mut World,
+    area: &[usize],
+    map_depth: i32,
+    rng: &mut RandomNumberGenerator,
+) {
+    let spawn_table = ecs.fetch::<RawMaster>().spawn_table_for_depth(map_depth);
+    let mut spawn_points: Vec<(usize, String)> = Vec::new();
+    let mut areas: Vec<usize> = Vec::from(area);
+
+    let num_spawns = i32::min(
+        areas.len() as i32,
+        rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3,
+    );
+
+    for _i in 0..num_spawns {
+        let array_index = if areas.len() == 1 {
+            0usize
+        } else {
+            (rng.roll_dice(1, areas.len() as i32) - 1) as usize
+        };
+        let map_idx = areas[array_index];
+        spawn_points.push((map_idx, spawn_table.roll(rng)));
+        areas.remove(array_index);
     }
 
     // Actually spawn the monsters
-    for spawn in spawn_points.iter() {
-        let x = (*spawn.0 % MAPWIDTH) as i32;
-        let y = (*spawn.0 / MAPWIDTH) as i32;
+    let map_width = ecs.fetch::<Map>().width as usize;
+    for (idx, name) in spawn_points.iter() {
+        let x = (*idx % map_width) as i32;
+        let y = (*idx / map_width) as i32;
 
-        match spawn.1.as_ref() {
-            "Rat" => rat(ecs, x, y),
-            "Goblin" => goblin(ecs, x, y),
-            "Orc" => orc(ecs, x, y),
-            "Health Potion" => health_potion(ecs, x, y),
-            "Fireball Scroll" => fireball_scroll(ecs, x, y),
-            "Confusion Scroll" => confusion_scroll(ecs, x, y),
-            "Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
-            "Dagger" => light_weapon(ecs, "Dagger".to_string(), x, y),
-            "Shield" => armor(ecs, "Shield".to_string(), x, y),
-            "Longsword" => martial_weapon(ecs, "Longsword".to_string(), x, y),
-            "Claymore" => great_weapon(ecs, "Claymore".to_string(), x, y),
-            _ => {}
-        }
+        spawn_named_entity(ecs, name, x, y);
     }
 }
 
-fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc", 16);
-}
-fn rat(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('r'), "Rat", 7);
-}
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin", 10);
-}
-
-fn monster<S: ToString>(
-    ecs: &mut World,
-    x: i32,
-    y: i32,
-    glyph: rltk::FontCharType,
-    name: S,
-    hp: i32,
-) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 1,
-        })
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 8,
-            dirty: true,
-        })
-        .with(Monster {})
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(BlocksTile {})
-        .with(CombatStats {
-            str: 0,
-            dex: 0,
-            con: 0,
-            int: 0,
-            wis: 0,
-            cha: 0,
-            max_hp: hp,
-            hp,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
+/// Places a closed door, which may be locked.
+pub fn door(ecs: &mut World, x: i32, y: i32, locked: bool) {
+    let mut builder = ecs
+        .create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::MAGENTA),
+            glyph: rltk::to_cp437('+'),
+            fg: RGB::named(rltk::CHOCOLATE),
             bg: RGB::named(rltk::BLACK),
             render_order: 2,
         })
         .with(Name {
-            name: "Health Potion".to_string(),
+            name: if locked { "Locked Door" } else { "Door" }.to_string(),
         })
-        .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesHealing { heal_amount: 8 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Magic Missile Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 20 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::ORANGE),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Fireball Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 20 })
-        .with(AreaOfEffect { radius: 3 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::PINK),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Confusion Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(Confusion { turns: 4 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn light_weapon(ecs: &mut World, name: std::string::String, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name { name })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { bonus: 0 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn martial_weapon(ecs: &mut World, name: std::string::String, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::GREEN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name { name })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { bonus: 1 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn great_weapon(ecs: &mut World, name: std::string::String, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::GREEN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name { name })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { bonus: 2 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn armor(ecs: &mut World, name: std::string::String, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('('),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name { name })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Armor,
-        })
-        .with(ArmorBonus { bonus: 1 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+        .with(Door { open: false })
+        .with(BlocksTile {})
+        .with(BlocksVisibility {});
+    if locked {
+        builder = builder.with(Locked {});
+    }
+    builder.marked::<SimpleMarker<SerializeMe>>().build();
 }
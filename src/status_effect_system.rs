@@ -0,0 +1,92 @@
+use super::{
+    gamelog::GameLog, CombatStats, Map, Name, Position, Status, StatusEffects, SufferDamage,
+    Viewshed,
+};
+use specs::prelude::*;
+
+/// Counts everyone's status effects down by a turn, whenever a turn passes. Poison and
+/// regeneration take or give a hit point as they tick; anything that runs out is removed.
+/// Blindness itself is applied by `VisibilitySystem`.
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadExpect<'a, Map>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            mut log,
+            mut status_effects,
+            mut combat_stats,
+            mut inflict_damage,
+            mut viewsheds,
+            names,
+            positions,
+            map,
+        ) = data;
+
+        let mut recovered: Vec<Entity> = Vec::new();
+        for (entity, statuses) in (&entities, &mut status_effects).join() {
+            for effect in statuses.effects.iter_mut() {
+                match effect.status {
+                    Status::Poisoned => {
+                        SufferDamage::new_damage(&mut inflict_damage, entity, 1, None);
+                    }
+                    Status::Regenerating => {
+                        if let Some(stats) = combat_stats.get_mut(entity) {
+                            stats.hp = i32::min(stats.max_hp, stats.hp + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                effect.turns -= 1;
+            }
+
+            // Blindness closes in (or lifts) the next time they look around
+            if statuses.has(Status::Blinded) {
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.dirty = true;
+                }
+            }
+
+            let in_view = positions
+                .get(entity)
+                .is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)]);
+            for effect in statuses.effects.iter().filter(|e| e.turns < 1) {
+                if entity == *player_entity {
+                    log.entries.push(format!(
+                        "You are no longer {}.",
+                        effect.status.name().to_lowercase()
+                    ));
+                } else if let (true, Some(name)) = (in_view, names.get(entity)) {
+                    log.entries.push(format!(
+                        "{} is no longer {}.",
+                        name.name,
+                        effect.status.name().to_lowercase()
+                    ));
+                }
+            }
+            statuses.effects.retain(|e| e.turns > 0);
+            if statuses.effects.is_empty() {
+                recovered.push(entity);
+            }
+        }
+
+        for entity in recovered {
+            status_effects.remove(entity);
+        }
+    }
+}
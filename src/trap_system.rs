@@ -0,0 +1,355 @@
+use super::{
+    character::{Ability, Skill, DEX, WIS},
+    gamelog::GameLog,
+    roll::{roll_2d6, Outcome},
+    Abilities, Alerted, Armor, CombatStats, Debilities, Debility, EntityMoved, Equipped,
+    Experience, Hidden, Map, Monster, Name, Position, RunState, Skills, SufferDamage, TileType,
+    Trap, TrapKind, Viewshed, WantsToSearch,
+};
+use rltk::{DistanceAlg, Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// How far a half-resisted teleport trap can throw you.
+const PARTIAL_TELEPORT_RANGE: f32 = 8.0;
+/// How far a muffled alarm still carries.
+const PARTIAL_ALARM_RANGE: f32 = 15.0;
+
+/// Springs traps on the tiles the player has just stepped onto. The dungeon's inhabitants know
+/// where their own traps are, so only the player sets them off. A 2d6+DEX roll decides how it
+/// goes: 10+ steps around the trap, 7-9 takes part of the effect and 6- takes all of it, along
+/// with any lasting harm the trap does.
+pub struct TrapSystem {}
+
+impl<'a> System<'a> for TrapSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, Point>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, EntityMoved>,
+        ReadStorage<'a, Trap>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, Alerted>,
+        ReadStorage<'a, Skills>,
+        ReadStorage<'a, Abilities>,
+        WriteStorage<'a, Experience>,
+        ReadStorage<'a, Armor>,
+        ReadStorage<'a, Equipped>,
+        WriteStorage<'a, Debilities>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            player_entity,
+            mut player_pos,
+            mut log,
+            mut rng,
+            mut entity_moved,
+            traps,
+            mut hidden,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            mut positions,
+            mut viewsheds,
+            monsters,
+            mut alerted,
+            skills,
+            abilities,
+            mut experience,
+            armor,
+            equipped,
+            mut debilities,
+        ) = data;
+
+        let movers: Vec<Entity> = (&entities, &entity_moved)
+            .join()
+            .map(|(entity, _)| entity)
+            .collect();
+        entity_moved.clear();
+
+        for mover in movers {
+            let pos = match positions.get(mover) {
+                Some(pos) => pos.clone(),
+                None => continue,
+            };
+            let idx = map.xy_idx(pos.x, pos.y);
+            let trap_entity = map.tile_content[idx]
+                .iter()
+                .find(|e| traps.get(**e).is_some())
+                .copied();
+            let trap_entity = match trap_entity {
+                Some(trap_entity) => trap_entity,
+                None => continue,
+            };
+
+            // Whatever happens next, the trap isn't a secret any more
+            hidden.remove(trap_entity);
+            let trap_name = &names.get(trap_entity).unwrap().name;
+
+            let dex = combat_stats.get(mover).map_or(0, |stats| stats.dex)
+                + Armor::heavy_penalty(&armor, &equipped, mover)
+                + Debilities::penalty(&debilities, mover, DEX);
+            let athletics = Skills::bonus(&skills, mover, Skill::Athletics);
+            let lightfooted = if Abilities::has(&abilities, mover, Ability::Lightfooted) {
+                1
+            } else {
+                0
+            };
+            let (_, outcome) = roll_2d6(&mut rng, dex + athletics + lightfooted);
+            if outcome == Outcome::Success {
+                log.entries.push(format!(
+                    "You spot the {} at the last moment, and step around it.",
+                    trap_name
+                ));
+                continue;
+            }
+            let full_effect = outcome == Outcome::Miss;
+            if full_effect {
+                Experience::mark(&mut experience, mover, 1);
+            }
+
+            let kind = traps.get(trap_entity).unwrap().kind;
+            match kind {
+                TrapKind::Pit => {
+                    let damage = if full_effect {
+                        rng.roll_dice(2, 6)
+                    } else {
+                        rng.roll_dice(1, 4)
+                    };
+                    if full_effect {
+                        log.entries
+                            .push(format!("You fall into the pit, for {} hp.", damage));
+                    } else {
+                        log.entries.push(format!(
+                            "You catch the edge of the pit and scramble out, for {} hp.",
+                            damage
+                        ));
+                    }
+                    SufferDamage::new_damage(&mut inflict_damage, mover, damage, None);
+                }
+                TrapKind::Darts => {
+                    let damage = if full_effect {
+                        rng.roll_dice(1, 8)
+                    } else {
+                        rng.roll_dice(1, 4)
+                    };
+                    if full_effect {
+                        log.entries.push(format!(
+                            "Darts fly from the walls, and hit you for {} hp.",
+                            damage
+                        ));
+                    } else {
+                        log.entries.push(format!(
+                            "You twist away from the darts, but one grazes you for {} hp.",
+                            damage
+                        ));
+                    }
+                    SufferDamage::new_damage(&mut inflict_damage, mover, damage, None);
+                }
+                TrapKind::Teleport => {
+                    let origin = Point::new(pos.x, pos.y);
+                    let destinations: Vec<usize> = map
+                        .tiles
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, tile)| **tile == TileType::Floor && !map.blocked[*i])
+                        .map(|(i, _)| i)
+                        .filter(|i| {
+                            full_effect
+                                || DistanceAlg::Pythagoras.distance2d(
+                                    origin,
+                                    Point::new(*i as i32 % map.width, *i as i32 / map.width),
+                                ) <= PARTIAL_TELEPORT_RANGE
+                        })
+                        .collect();
+                    if destinations.is_empty() {
+                        log.entries
+                            .push("The air shimmers around you, then settles.".to_string());
+                        continue;
+                    }
+                    let destination =
+                        destinations[(rng.roll_dice(1, destinations.len() as i32) - 1) as usize];
+                    if let Some(pos) = positions.get_mut(mover) {
+                        pos.x = destination as i32 % map.width;
+                        pos.y = destination as i32 / map.width;
+                        if mover == *player_entity {
+                            player_pos.x = pos.x;
+                            player_pos.y = pos.y;
+                        }
+                    }
+                    if let Some(viewshed) = viewsheds.get_mut(mover) {
+                        viewshed.dirty = true;
+                    }
+                    if full_effect {
+                        log.entries.push(
+                            "The world lurches, and you find yourself somewhere else.".to_string(),
+                        );
+                    } else {
+                        log.entries
+                            .push("You lurch sideways through space, but not far.".to_string());
+                    }
+                }
+                TrapKind::Alarm => {
+                    let origin = Point::new(pos.x, pos.y);
+                    for (monster_entity, _monster, monster_pos) in
+                        (&entities, &monsters, &positions).join()
+                    {
+                        let distance = DistanceAlg::Pythagoras
+                            .distance2d(origin, Point::new(monster_pos.x, monster_pos.y));
+                        if full_effect || distance <= PARTIAL_ALARM_RANGE {
+                            alerted
+                                .insert(monster_entity, Alerted { x: pos.x, y: pos.y })
+                                .expect("Unable to insert alert");
+                        }
+                    }
+                    if full_effect {
+                        log.entries.push(
+                            "A bell clangs, loud enough to wake the whole level!".to_string(),
+                        );
+                    } else {
+                        log.entries.push(
+                            "You grab the bell, but not before it rings out nearby.".to_string(),
+                        );
+                    }
+                }
+            }
+
+            // A trap sprung in full can leave its mark
+            let lasting = match kind {
+                TrapKind::Pit => Some(Debility::Shaky),
+                TrapKind::Darts => Some(Debility::Sick),
+                TrapKind::Teleport => Some(Debility::Confused),
+                TrapKind::Alarm => None,
+            };
+            if let (true, Some(debility)) = (full_effect, lasting) {
+                if Debilities::inflict(&mut debilities, mover, debility) {
+                    log.entries
+                        .push(format!("You are left {}.", debility.describe()));
+                }
+            }
+        }
+    }
+}
+
+/// How close a trap has to be for the player to notice it without looking.
+const PASSIVE_RANGE: f32 = 2.5;
+/// How far a search turns up traps when it only partly succeeds.
+const PARTIAL_SEARCH_RANGE: f32 = 4.0;
+
+/// Gives the player a 2d6+WIS check each turn to notice hidden traps close by. Searching on
+/// purpose looks further: 10+ finds every trap in view, 7-9 those nearby.
+pub struct TrapDetectionSystem {}
+
+impl<'a> System<'a> for TrapDetectionSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, WantsToSearch>,
+        ReadStorage<'a, Trap>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Viewshed>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Skills>,
+        ReadStorage<'a, Abilities>,
+        WriteStorage<'a, Experience>,
+        ReadStorage<'a, Debilities>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player_entity,
+            runstate,
+            mut log,
+            mut rng,
+            mut wants_search,
+            traps,
+            mut hidden,
+            names,
+            positions,
+            viewsheds,
+            combat_stats,
+            skills,
+            abilities,
+            mut experience,
+            debilities,
+        ) = data;
+
+        if *runstate != RunState::PlayerTurn {
+            return;
+        }
+
+        let searching = wants_search.get(*player_entity).is_some();
+        wants_search.clear();
+
+        let (player_pos, viewshed) =
+            match (positions.get(*player_entity), viewsheds.get(*player_entity)) {
+                (Some(pos), Some(viewshed)) => (Point::new(pos.x, pos.y), viewshed),
+                _ => return,
+            };
+        let wis = combat_stats
+            .get(*player_entity)
+            .map_or(0, |stats| stats.wis)
+            + Debilities::penalty(&debilities, *player_entity, WIS);
+        let awareness = Skills::bonus(&skills, *player_entity, Skill::Awareness);
+        let keen_eyed = if Abilities::has(&abilities, *player_entity, Ability::KeenEyed) {
+            1
+        } else {
+            0
+        };
+        let (_, outcome) = roll_2d6(&mut rng, wis + awareness + keen_eyed);
+        // Only a search the player chose to make counts as a failed roll
+        if searching && outcome == Outcome::Miss {
+            Experience::mark(&mut experience, *player_entity, 1);
+        }
+        let range = match (searching, outcome) {
+            (true, Outcome::Success) => Some(viewshed.range as f32),
+            (true, Outcome::Partial) => Some(PARTIAL_SEARCH_RANGE),
+            (false, Outcome::Success) => Some(PASSIVE_RANGE),
+            _ => None,
+        };
+
+        let mut found: Vec<Entity> = Vec::new();
+        if let Some(range) = range {
+            for (trap_entity, _trap, _hidden, pos) in
+                (&entities, &traps, &hidden, &positions).join()
+            {
+                let trap_pos = Point::new(pos.x, pos.y);
+                if viewshed.visible_tiles.contains(&trap_pos)
+                    && DistanceAlg::Pythagoras.distance2d(player_pos, trap_pos) <= range
+                {
+                    found.push(trap_entity);
+                }
+            }
+        }
+
+        for trap_entity in found.iter() {
+            hidden.remove(*trap_entity);
+            if let Some(name) = names.get(*trap_entity) {
+                log.entries.push(format!("You spot a {}.", name.name));
+            }
+        }
+        if searching && found.is_empty() {
+            log.entries
+                .push("You search carefully, but find nothing.".to_string());
+        }
+    }
+}
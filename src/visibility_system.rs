@@ -1,7 +1,9 @@
-use super::{Map, Player, Position, Viewshed};
+use super::{Map, PartyMember, Position, Status, StatusEffects, Viewshed};
 use rltk::{field_of_view, Point};
 use specs::prelude::*;
 
+/// Works out what everyone can see. The party share what they see with each other, so the map
+/// shows whatever any of them has in view.
 pub struct VisibilitySystem {}
 
 impl<'a> System<'a> for VisibilitySystem {
@@ -10,32 +12,39 @@ impl<'a> System<'a> for VisibilitySystem {
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         ReadStorage<'a, Position>,
-        ReadStorage<'a, Player>,
+        ReadStorage<'a, PartyMember>,
+        ReadStorage<'a, StatusEffects>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player) = data;
+        let (mut map, entities, mut viewshed, pos, members, status_effects) = data;
 
+        let mut party_moved = false;
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
                 viewshed.dirty = false;
-                viewshed.visible_tiles =
-                    field_of_view(Point::new(pos.x, pos.y), viewshed.range, &*map);
+                let blinded = status_effects
+                    .get(ent)
+                    .is_some_and(|s| s.has(Status::Blinded));
+                let range = if blinded { 1 } else { viewshed.range };
+                viewshed.visible_tiles = field_of_view(Point::new(pos.x, pos.y), range, &*map);
                 viewshed
                     .visible_tiles
                     .retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
+                party_moved |= members.get(ent).is_some();
+            }
+        }
 
-                // If this is the player, reveal what they can see
-                let _p: Option<&Player> = player.get(ent);
-                if let Some(_p) = _p {
-                    for t in map.visible_tiles.iter_mut() {
-                        *t = false
-                    }
-                    for vis in viewshed.visible_tiles.iter() {
-                        let idx = map.xy_idx(vis.x, vis.y);
-                        map.revealed_tiles[idx] = true;
-                        map.visible_tiles[idx] = true;
-                    }
+        // If anyone in the party sees something new, reveal what they can all see
+        if party_moved {
+            for t in map.visible_tiles.iter_mut() {
+                *t = false
+            }
+            for (viewshed, _member) in (&viewshed, &members).join() {
+                for vis in viewshed.visible_tiles.iter() {
+                    let idx = map.xy_idx(vis.x, vis.y);
+                    map.revealed_tiles[idx] = true;
+                    map.visible_tiles[idx] = true;
                 }
             }
         }